@@ -4,6 +4,7 @@ set -euxo pipefail
 
 main() {
     cargo check --target $TARGET
+    cargo check --target $TARGET --all-features
 }
 
 main
@@ -0,0 +1,304 @@
+//! Frame by frame animations for 8x8 LED matrices and intensity effects
+//!
+//! Frames use the same layout as `write_matrix`, so orientation
+//! and flips set on the display apply to every frame.
+
+use crate::connectors::Connector;
+#[cfg(feature = "matrix")]
+use crate::MAX_DIGITS;
+use crate::{check_intensity, DataError, GAMMA16, MAX7219};
+
+///
+/// Plays a sequence of frames on one matrix, one frame per `tick`.
+/// Loops by default, see `set_loop` to play it once.
+///
+#[cfg(feature = "matrix")]
+pub struct FrameAnimation<'a> {
+    frames: &'a [[u8; MAX_DIGITS]],
+    index: usize,
+    looping: bool,
+}
+
+#[cfg(feature = "matrix")]
+impl<'a> FrameAnimation<'a> {
+    ///
+    /// Construct a new animation starting with the first of given frames
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - the frames to play, see `write_matrix` for the bit layout
+    ///
+    pub fn new(frames: &'a [[u8; MAX_DIGITS]]) -> Self {
+        FrameAnimation {
+            frames,
+            index: 0,
+            looping: true,
+        }
+    }
+
+    ///
+    /// Sets whether the animation starts over after the last frame
+    /// or stops showing it
+    ///
+    pub fn set_loop(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    ///
+    /// Writes the next frame to the display. Returns `true` once the last frame
+    /// is shown, after which a looping animation starts over with the next tick
+    /// while a non-looping one keeps the last frame without writing anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - the driver of the matrix to write to
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn tick<CONNECTOR, const N: usize>(
+        &mut self,
+        display: &mut MAX7219<CONNECTOR, N>,
+        addr: usize,
+    ) -> Result<bool, DataError>
+    where
+        CONNECTOR: Connector,
+    {
+        if self.index >= self.frames.len() {
+            if !self.looping || self.frames.is_empty() {
+                return Ok(true);
+            }
+            self.index = 0;
+        }
+
+        display.write_matrix(addr, &self.frames[self.index])?;
+        self.index += 1;
+
+        Ok(self.index >= self.frames.len())
+    }
+
+    ///
+    /// Starts the animation over from the first frame on next `tick`
+    ///
+    pub fn reset(&mut self) {
+        self.index = 0;
+    }
+}
+
+///
+/// Breathing effect ramping the intensity of a display up and down between
+/// two bounds, one step per `tick`. Steps are taken in perceptual brightness
+/// mapped through `GAMMA16`, so the ramp looks even in both directions.
+///
+pub struct Breather {
+    min: u8,
+    max: u8,
+    speed: u8,
+    phase: u8,
+    rising: bool,
+}
+
+impl Breather {
+    ///
+    /// Construct a new breather starting at `min` and rising, advancing
+    /// by one perceptual step per tick
+    ///
+    /// # Arguments
+    ///
+    /// * `min` - the lowest intensity to ramp down to `0x00` to `0x0F`
+    /// * `max` - the highest intensity to ramp up to `0x00` to `0x0F`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if `min` or `max` is above `0x0F`
+    /// * `DataError::InvalidRange` - returned if `min` is above `max`
+    ///
+    pub fn new(min: u8, max: u8) -> Result<Self, DataError> {
+        check_intensity(min)?;
+        check_intensity(max)?;
+        if min > max {
+            return Err(DataError::InvalidRange);
+        }
+
+        Ok(Breather {
+            min,
+            max,
+            speed: 1,
+            phase: 0,
+            rising: true,
+        })
+    }
+
+    ///
+    /// Sets how many perceptual steps, out of 255 from `min` to `max`,
+    /// the ramp advances per tick. A speed of `0` is taken as `1`.
+    ///
+    pub fn set_speed(&mut self, speed: u8) {
+        self.speed = speed.max(1);
+    }
+
+    ///
+    /// Advances the ramp and sets the resulting intensity on the display,
+    /// reversing direction at `min` and `max`. Nothing is sent if the
+    /// intensity did not change since the last step.
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - the driver of the display to write to
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn tick<CONNECTOR, const N: usize>(
+        &mut self,
+        display: &mut MAX7219<CONNECTOR, N>,
+        addr: usize,
+    ) -> Result<(), DataError>
+    where
+        CONNECTOR: Connector,
+    {
+        display.check_addr(addr)?;
+
+        if self.rising {
+            match self.phase.checked_add(self.speed) {
+                Some(phase) => self.phase = phase,
+                None => {
+                    self.phase = u8::MAX;
+                    self.rising = false;
+                }
+            }
+        } else {
+            match self.phase.checked_sub(self.speed) {
+                Some(phase) => self.phase = phase,
+                None => {
+                    self.phase = 0;
+                    self.rising = true;
+                }
+            }
+        }
+
+        let span = u16::from(self.max - self.min);
+        let intensity = self.min + (u16::from(GAMMA16[self.phase as usize]) * span / 0x0F) as u8;
+        if display.intensities[addr] == intensity {
+            return Ok(());
+        }
+
+        display.set_intensity(addr, intensity)?;
+        Ok(())
+    }
+
+    ///
+    /// Starts the ramp over from `min` on next `tick`
+    ///
+    pub fn reset(&mut self) {
+        self.phase = 0;
+        self.rising = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::display;
+    use crate::Command;
+
+    #[test]
+    fn rejects_invalid_breather_bounds() {
+        assert_eq!(
+            Breather::new(0x10, 0x0F).err(),
+            Some(DataError::InvalidIntensity(0x10))
+        );
+        assert_eq!(
+            Breather::new(0x00, 0x10).err(),
+            Some(DataError::InvalidIntensity(0x10))
+        );
+        assert_eq!(
+            Breather::new(0x09, 0x03).err(),
+            Some(DataError::InvalidRange)
+        );
+        assert!(Breather::new(0x03, 0x03).is_ok());
+    }
+
+    #[test]
+    fn breathes_up_and_down_between_bounds() {
+        let intensity = Command::Intensity as u8;
+        let mut chain = display(2);
+        let mut breather = Breather::new(2, 8).unwrap();
+        breather.set_speed(128);
+
+        // phases 128, 255 (clamped), 127, 0 (clamped) and 128 again
+        for _ in 0..5 {
+            breather.tick(&mut chain, 1).unwrap();
+        }
+        assert!(chain.connector().commands().eq([
+            (1, intensity, 3),
+            (1, intensity, 8),
+            (1, intensity, 3),
+            (1, intensity, 2),
+            (1, intensity, 3),
+        ]
+        .iter()
+        .copied()));
+
+        // the turning points repeat the bound without sending it again
+        let mut display = display(1);
+        let mut breather = Breather::new(2, 8).unwrap();
+        breather.set_speed(255);
+        for _ in 0..5 {
+            breather.tick(&mut display, 0).unwrap();
+        }
+        assert!(display.connector().commands().eq([
+            (0, intensity, 8),
+            (0, intensity, 2),
+            (0, intensity, 8),
+        ]
+        .iter()
+        .copied()));
+
+        breather.reset();
+        breather.tick(&mut display, 0).unwrap();
+        assert_eq!(
+            display.connector().commands().last(),
+            Some((0, intensity, 8))
+        );
+        assert_eq!(
+            breather.tick(&mut display, 1).err(),
+            Some(DataError::AddressOutOfRange(1))
+        );
+    }
+
+    #[cfg(feature = "matrix")]
+    #[test]
+    fn plays_frames_looping_or_once() {
+        let frames = [[0x81; MAX_DIGITS], [0x42; MAX_DIGITS], [0x24; MAX_DIGITS]];
+        let mut display = display(2);
+        let mut animation = FrameAnimation::new(&frames);
+
+        for (i, done) in [false, false, true, false].iter().enumerate() {
+            assert_eq!(animation.tick(&mut display, 1), Ok(*done));
+            assert_eq!(display.get_row(1, 0), Ok(frames[i % frames.len()][0]));
+        }
+        assert_eq!(display.get_row(0, 0), Ok(0));
+
+        // a non-looping animation keeps its last frame without writing
+        animation.reset();
+        animation.set_loop(false);
+        for done in [false, false, true].iter() {
+            assert_eq!(animation.tick(&mut display, 1), Ok(*done));
+        }
+        display.connector_mut().clear();
+        assert_eq!(animation.tick(&mut display, 1), Ok(true));
+        assert_eq!(display.connector().frame_count(), 0);
+        assert_eq!(display.get_row(1, 0), Ok(0x24));
+
+        let mut empty = FrameAnimation::new(&[]);
+        assert_eq!(empty.tick(&mut display, 1), Ok(true));
+        assert_eq!(display.connector().frame_count(), 0);
+    }
+}
@@ -0,0 +1,598 @@
+//! Async driver variant using `embedded-hal-async`
+//!
+//! `MAX7219Async` sends the same commands as `MAX7219` but awaits each
+//! transfer on an `SpiDevice`, which also controls the CS (LOAD) line,
+//! so other tasks keep running during long shift-outs on big chains.
+//! Frames are built by the same helpers as for `MAX7219`.
+
+use embedded_hal_async::spi::{Error as _, SpiDevice};
+
+use crate::builder::Settings;
+use crate::frame::{self, DigitPairs, Setting};
+#[cfg(feature = "matrix")]
+use crate::matrix::{Layout, Orientation};
+use crate::{check_intensity, Command, DataError, DecodeMode, Digit, MAX_DIGITS, MAX_DISPLAYS};
+
+///
+/// Handles communication with the MAX7219 chip over an async SPI device.
+/// `N` is the maximum number of displays supported by this instance
+/// which sizes the internal buffer, `MAX_DISPLAYS` by default.
+///
+pub struct MAX7219Async<SPI, const N: usize = MAX_DISPLAYS> {
+    spi: SPI,
+    devices: usize,
+    buffer: [[u8; 2]; N],
+    single: Option<usize>,
+    decode_masks: [u8; N],
+    #[cfg(feature = "matrix")]
+    layouts: [Layout; N],
+}
+
+impl<SPI, const N: usize> MAX7219Async<SPI, N>
+where
+    SPI: SpiDevice,
+{
+    ///
+    /// Construct and initialize a new async MAX7219 driver instance, turning test mode
+    /// off, scanning all digits without decode, clearing and powering the displays off.
+    ///
+    /// # Arguments
+    ///
+    /// * `displays` - number of displays connected in series, 1 to `N`
+    /// * `spi` - the SPI device controlling the CS (LOAD) line, in MODE_0 with max 10 Mhz frequency
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `N`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn new(displays: usize, spi: SPI) -> Result<Self, DataError> {
+        if displays == 0 || displays > N {
+            return Err(DataError::InvalidDisplayCount);
+        }
+
+        let mut max7219 = MAX7219Async {
+            spi,
+            devices: displays,
+            buffer: [[0; 2]; N],
+            single: None,
+            decode_masks: [DecodeMode::NoDecode as u8; N],
+            #[cfg(feature = "matrix")]
+            layouts: [Layout::NORMAL; N],
+        };
+
+        max7219.init().await?;
+        Ok(max7219)
+    }
+
+    ///
+    /// Powers on all connected displays
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn power_on(&mut self) -> Result<(), DataError> {
+        self.write_data_all(Command::Power, 0x01).await
+    }
+
+    ///
+    /// Powers off all connected displays
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn power_off(&mut self) -> Result<(), DataError> {
+        self.write_data_all(Command::Power, 0x00).await
+    }
+
+    ///
+    /// Clears all connected displays by setting all digits to empty,
+    /// blanking each digit as decoded by its display
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn clear_all(&mut self) -> Result<(), DataError> {
+        for digit in Digit::all() {
+            frame::fill_blanks(&mut self.buffer, &self.decode_masks, digit);
+            self.write_buffer().await?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Sets intensity level on the display, `0x00` (dimmest) to `0x0F` (brightest)
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `intensity` - intensity value to set to `0x00` to `0x0F`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if `intensity` is above `0x0F`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn set_intensity(&mut self, addr: usize, intensity: u8) -> Result<(), DataError> {
+        check_intensity(intensity)?;
+
+        self.write_data(addr, Command::Intensity, intensity).await
+    }
+
+    ///
+    /// Sets intensity level on all connected displays at once
+    ///
+    /// # Arguments
+    ///
+    /// * `intensity` - intensity value to set to `0x00` to `0x0F`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if `intensity` is above `0x0F`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn set_intensity_all(&mut self, intensity: u8) -> Result<(), DataError> {
+        check_intensity(intensity)?;
+
+        self.write_data_all(Command::Intensity, intensity).await
+    }
+
+    ///
+    /// Sets decode mode to be used on input sent to the display chip.
+    /// Nothing is sent if the display already uses given mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `mode` - the decode mode to set
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn set_decode_mode(
+        &mut self,
+        addr: usize,
+        mode: DecodeMode,
+    ) -> Result<(), DataError> {
+        frame::check_addr(addr, self.devices)?;
+        if self.decode_masks[addr] == mode as u8 {
+            return Ok(());
+        }
+
+        self.write_data(addr, Command::DecodeMode, mode as u8).await
+    }
+
+    ///
+    /// Writes byte string to the display, see `MAX7219::write_str`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `string` - the byte string to send 8 bytes long. Unknown characters result in question mark.
+    /// * `dots` - u8 bit array specifying where to put dots in the string (1 = dot, 0 = not)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn write_str(
+        &mut self,
+        addr: usize,
+        string: &[u8; MAX_DIGITS],
+        dots: u8,
+    ) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::NoDecode).await?;
+
+        self.write_pairs(addr, &frame::str_pairs(string, dots))
+            .await
+    }
+
+    ///
+    /// Writes BCD encoded string to the display, see `MAX7219::write_bcd`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `bcd` - the bcd encoded string slice consisting of [0-9,-,E,L,H,P]
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn write_bcd(
+        &mut self,
+        addr: usize,
+        bcd: &[u8; MAX_DIGITS],
+    ) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::CodeBDigits7_0)
+            .await?;
+
+        self.write_pairs(addr, &frame::bcd_pairs(bcd)).await
+    }
+
+    ///
+    /// Writes a raw value to the display, see `MAX7219::write_raw`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `raw` - an array of raw bytes to write. Each bit represents a pixel on the display
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn write_raw(
+        &mut self,
+        addr: usize,
+        raw: &[u8; MAX_DIGITS],
+    ) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::NoDecode).await?;
+
+        self.write_pairs(addr, &frame::raw_pairs(raw)).await
+    }
+
+    ///
+    /// Writes a full 8x8 frame to the matrix, see `MAX7219::write_matrix`.
+    /// The orientation and flips set on the matrix are applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `frame` - 8 rows of pixels, most significant bit being column 0
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    #[cfg(feature = "matrix")]
+    pub async fn write_matrix(
+        &mut self,
+        addr: usize,
+        frame: &[u8; MAX_DIGITS],
+    ) -> Result<(), DataError> {
+        frame::check_addr(addr, self.devices)?;
+
+        let frame = self.layouts[addr].frame(frame);
+        self.write_pairs(addr, &frame::raw_pairs(&frame)).await
+    }
+
+    ///
+    /// Sets the orientation of given matrix, see `MAX7219::set_orientation`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `orientation` - the clockwise rotation of the content
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    #[cfg(feature = "matrix")]
+    pub fn set_orientation(
+        &mut self,
+        addr: usize,
+        orientation: Orientation,
+    ) -> Result<(), DataError> {
+        frame::check_addr(addr, self.devices)?;
+
+        self.layouts[addr].orientation = orientation;
+        Ok(())
+    }
+
+    ///
+    /// Sets whether given matrix is mirrored, see `MAX7219::set_flip`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `flip_h` - whether to mirror columns, reversing the bits of each row
+    /// * `flip_v` - whether to mirror rows, reversing which register each row goes to
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    #[cfg(feature = "matrix")]
+    pub fn set_flip(&mut self, addr: usize, flip_h: bool, flip_v: bool) -> Result<(), DataError> {
+        frame::check_addr(addr, self.devices)?;
+
+        self.layouts[addr].flip_h = flip_h;
+        self.layouts[addr].flip_v = flip_v;
+        Ok(())
+    }
+
+    ///
+    /// Writes raw 8 row frames to every connected display in 8 transfers,
+    /// see `MAX7219::write_frames`
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - one frame per display as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if there is not exactly one frame per display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn write_frames(&mut self, frames: &[[u8; MAX_DIGITS]]) -> Result<(), DataError> {
+        if frames.len() != self.devices {
+            return Err(DataError::InvalidLength);
+        }
+
+        for row in 0..MAX_DIGITS {
+            frame::fill_row(&mut self.buffer, row, frames);
+            self.write_buffer().await?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Set test mode on/off
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `is_on` - whether to turn test mode on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn test(&mut self, addr: usize, is_on: bool) -> Result<(), DataError> {
+        self.write_data(addr, Command::DisplayTest, is_on as u8)
+            .await
+    }
+
+    ///
+    /// Returns the number of displays connected in series
+    ///
+    pub fn device_count(&self) -> usize {
+        self.devices
+    }
+
+    ///
+    /// Writes data to given register as described by command
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `command` - the command/register on the display to write to
+    /// * `data` - the data byte value to write
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn write_data(
+        &mut self,
+        addr: usize,
+        command: Command,
+        data: u8,
+    ) -> Result<(), DataError> {
+        self.write_register(addr, command as u8, data).await
+    }
+
+    ///
+    /// Writes data to given register as described by command on all
+    /// connected displays at once
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - the command/register on the displays to write to
+    /// * `data` - the data byte value to write
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub async fn write_data_all(&mut self, command: Command, data: u8) -> Result<(), DataError> {
+        self.write_register_all(command as u8, data).await
+    }
+
+    // writes the digit registers of given display one after the other
+    async fn write_pairs(&mut self, addr: usize, pairs: &DigitPairs) -> Result<(), DataError> {
+        for [digit, data] in pairs.iter() {
+            self.write_register(addr, *digit, *data).await?;
+        }
+
+        Ok(())
+    }
+
+    // writes to a single register on given display, sending no-ops to the rest
+    async fn write_register(&mut self, addr: usize, header: u8, data: u8) -> Result<(), DataError> {
+        frame::check_addr(addr, self.devices)?;
+
+        frame::fill_register(&mut self.buffer, self.single, addr, header, data);
+        self.write_buffer().await?;
+        self.single = Some(addr);
+        Ok(())
+    }
+
+    // writes the same register and value to all connected displays
+    async fn write_register_all(&mut self, header: u8, data: u8) -> Result<(), DataError> {
+        self.buffer = [[header, data]; N];
+
+        self.write_buffer().await
+    }
+
+    // shifts the buffer out to all connected displays in one CS cycle,
+    // remembering the decode mode of each display once it was sent
+    async fn write_buffer(&mut self) -> Result<(), DataError> {
+        self.single = None;
+
+        self.spi
+            .write(self.buffer[..self.devices].as_flattened())
+            .await
+            .map_err(|e| e.kind())?;
+
+        for (mask, [header, data]) in self.decode_masks.iter_mut().zip(self.buffer.iter()) {
+            if let Some(Setting::DecodeMode(sent)) = Setting::sent(*header, *data) {
+                *mask = sent;
+            }
+        }
+
+        Ok(())
+    }
+
+    // runs the same initialization sequence as `MAX7219` with default settings
+    async fn init(&mut self) -> Result<(), DataError> {
+        let settings = Settings::default();
+
+        for (command, data) in frame::init_commands(&settings) {
+            self.write_data_all(command, data).await?;
+        }
+        self.clear_all().await?; // clear all digits
+        let (command, data) = frame::power_command(&settings);
+        self.write_data_all(command, data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll};
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    use embedded_hal_async::spi::{ErrorKind, ErrorType, Operation};
+
+    use super::*;
+    use crate::connectors::Connector;
+    use crate::mock::RecordingConnector;
+    use crate::test_util::initialized as blocking;
+
+    // SPI device recording each transaction as a frame
+    struct RecordingSpi(RecordingConnector);
+
+    impl ErrorType for RecordingSpi {
+        type Error = ErrorKind;
+    }
+
+    impl SpiDevice for RecordingSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations.iter() {
+                if let Operation::Write(bytes) = operation {
+                    self.0.write_frame(bytes).map_err(|_| ErrorKind::Other)?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // polls given future on the current thread until it completes
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut context = Context::from_waker(&waker);
+        let mut future = pin!(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    type Display = MAX7219Async<RecordingSpi>;
+
+    // connects `devices` displays, keeping the initialization frames
+    fn display(devices: usize) -> Display {
+        block_on(MAX7219Async::new(
+            devices,
+            RecordingSpi(RecordingConnector::new()),
+        ))
+        .expect("valid display count")
+    }
+
+    #[test]
+    fn sends_the_same_frames_as_the_blocking_driver() {
+        let mut display = display(2);
+        let mut expected = blocking(2);
+        assert_eq!(display.spi.0.bytes(), expected.connector().bytes());
+
+        block_on(display.write_str(1, b"Hello 42", 0b0000_0100)).unwrap();
+        block_on(display.write_bcd(0, b"-12.E  9")).unwrap();
+        block_on(display.clear_all()).unwrap();
+        expected.write_str(1, b"Hello 42", 0b0000_0100).unwrap();
+        expected.write_bcd(0, b"-12.E  9").unwrap();
+        expected.clear_all().unwrap();
+
+        assert_eq!(display.spi.0.bytes(), expected.connector().bytes());
+    }
+
+    #[test]
+    fn skips_unchanged_decode_mode() {
+        let mut display = display(1);
+        display.spi.0.clear();
+
+        block_on(display.set_decode_mode(0, DecodeMode::NoDecode)).unwrap();
+        assert_eq!(display.spi.0.frame_count(), 0);
+
+        block_on(display.set_decode_mode(0, DecodeMode::CodeBDigits7_0)).unwrap();
+        block_on(display.set_decode_mode(0, DecodeMode::CodeBDigits7_0)).unwrap();
+        assert!(display
+            .spi
+            .0
+            .commands()
+            .eq([(0, 0x09, 0xFF)].iter().copied()));
+
+        let outside = block_on(display.set_decode_mode(1, DecodeMode::NoDecode)).err();
+        assert_eq!(outside, Some(DataError::AddressOutOfRange(1)));
+    }
+
+    #[test]
+    fn write_matrix_applies_layout() {
+        let mut display = display(1);
+        let mut expected = blocking(1);
+        let sprite = [0b1110_0000, 0b1000_0000, 0b1100_0000, 0, 0, 0, 0, 0];
+
+        display.set_orientation(0, Orientation::Rotate90).unwrap();
+        display.set_flip(0, true, false).unwrap();
+        display.spi.0.clear();
+        block_on(display.write_matrix(0, &sprite)).unwrap();
+
+        expected.set_orientation(0, Orientation::Rotate90).unwrap();
+        expected.set_flip(0, true, false).unwrap();
+        expected.connector_mut().clear();
+        expected.write_matrix(0, &sprite).unwrap();
+
+        assert_eq!(display.spi.0.bytes(), expected.connector().bytes());
+        // rotated and mirrored back the sprite ends up transposed
+        let rows = display.spi.0.commands().take(3);
+        assert!(rows.eq([
+            (0, 0x01, 0b1110_0000),
+            (0, 0x02, 0b1010_0000),
+            (0, 0x03, 0b1000_0000)
+        ]
+        .iter()
+        .copied()));
+    }
+}
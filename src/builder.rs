@@ -0,0 +1,250 @@
+//! Builder configuring how displays are initialized
+
+use embedded_hal::blocking::spi::Write;
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::connectors::*;
+#[cfg(feature = "matrix")]
+use crate::matrix::Layout;
+#[cfg(feature = "segment")]
+use crate::segment::{Indicators, OverflowBehavior, SegmentPolarity};
+use crate::{check_intensity, Command, DataError, DecodeMode, DeviceError, MAX7219, MAX_DIGITS};
+
+/// Settings applied to all displays during initialization and `reset`
+#[derive(Clone, Copy)]
+pub(crate) struct Settings {
+    pub(crate) intensity: Option<u8>,
+    pub(crate) decode_mode: DecodeMode,
+    pub(crate) scan_limit: u8,
+    pub(crate) power_on: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            intensity: None,
+            decode_mode: DecodeMode::NoDecode,
+            scan_limit: MAX_DIGITS as u8,
+            power_on: false,
+        }
+    }
+}
+
+///
+/// Builds a MAX7219 driver instance with custom initialization settings.
+/// By default all digits are scanned without decode, the intensity is left
+/// as is and the displays are powered off after being cleared, same as
+/// with the `MAX7219` constructor functions.
+///
+#[derive(Clone, Copy)]
+pub struct MAX7219Builder {
+    displays: usize,
+    settings: Settings,
+}
+
+impl MAX7219Builder {
+    ///
+    /// Construct a new builder with default settings
+    ///
+    /// # Arguments
+    ///
+    /// * `displays` - number of displays connected in series
+    ///
+    pub fn new(displays: usize) -> Self {
+        MAX7219Builder {
+            displays,
+            settings: Settings::default(),
+        }
+    }
+
+    ///
+    /// Sets the intensity all displays start with, `0x00` to `0x0F`
+    ///
+    pub fn intensity(mut self, intensity: u8) -> Self {
+        self.settings.intensity = Some(intensity);
+        self
+    }
+
+    ///
+    /// Sets the decode mode all displays start with
+    ///
+    pub fn decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.settings.decode_mode = mode;
+        self
+    }
+
+    ///
+    /// Sets how many digits all displays scan, `1` to `8`. All 8 are scanned
+    /// unless set. Modules wired with fewer digits should set their count, since
+    /// scanning unused digit lines can cause ghosting on the connected ones, at
+    /// the cost of the remaining digits appearing brighter. See
+    /// `MAX7219::set_scan_limit` for changing it on a single display.
+    ///
+    pub fn scan_limit(mut self, digits: u8) -> Self {
+        self.settings.scan_limit = digits;
+        self
+    }
+
+    ///
+    /// Sets whether the displays are powered on once initialized
+    ///
+    pub fn power_on_after_init(mut self, power_on: bool) -> Self {
+        self.settings.power_on = power_on;
+        self
+    }
+
+    ///
+    /// Builds and initializes a MAX7219 driver instance using given connector,
+    /// supporting up to `N` displays connected in series.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if number of displays is 0 or exceeds `N`
+    /// * `DataError::InvalidIntensity` - returned if the intensity is above `0x0F`
+    /// * `DataError::InvalidScanLimit` - returned if the scan limit is not within `1` to `8`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn build<CONNECTOR, const N: usize>(
+        self,
+        connector: CONNECTOR,
+    ) -> Result<MAX7219<CONNECTOR, N>, DataError>
+    where
+        CONNECTOR: Connector,
+    {
+        let mut max7219 = self.assemble(connector)?;
+
+        max7219.init()?;
+        Ok(max7219)
+    }
+
+    ///
+    /// Builds a MAX7219 driver instance using given connector like `build`, but
+    /// initializes the displays one by one and `verify`s them, so failures tell
+    /// which display and command were being written. This sends one transfer
+    /// per display and command instead of one per command.
+    ///
+    /// # Errors
+    ///
+    /// * `DeviceError` - returned with the display and command being written in case
+    ///   there was an error during data transfer, or for invalid settings as listed
+    ///   by `build` with display `0` and `Command::Noop` as nothing was written
+    ///
+    pub fn build_verified<CONNECTOR, const N: usize>(
+        self,
+        connector: CONNECTOR,
+    ) -> Result<MAX7219<CONNECTOR, N>, DeviceError>
+    where
+        CONNECTOR: Connector,
+    {
+        let mut max7219 = self.assemble(connector).map_err(|error| DeviceError {
+            addr: 0,
+            command: Command::Noop,
+            error,
+        })?;
+
+        max7219.init_each()?;
+        max7219.verify()?;
+        Ok(max7219)
+    }
+
+    // validates the settings and creates the driver without initializing displays
+    fn assemble<CONNECTOR, const N: usize>(
+        self,
+        connector: CONNECTOR,
+    ) -> Result<MAX7219<CONNECTOR, N>, DataError>
+    where
+        CONNECTOR: Connector,
+    {
+        if self.displays == 0 || self.displays > N {
+            return Err(DataError::InvalidDisplayCount);
+        }
+        if let Some(intensity) = self.settings.intensity {
+            check_intensity(intensity)?;
+        }
+        if self.settings.scan_limit == 0 || self.settings.scan_limit as usize > MAX_DIGITS {
+            return Err(DataError::InvalidScanLimit);
+        }
+
+        Ok(MAX7219 {
+            c: connector,
+            devices: self.displays,
+            buffer: [[0; 2]; N],
+            single: None,
+            digits: [[0; MAX_DIGITS]; N],
+            decode_masks: [self.settings.decode_mode as u8; N],
+            #[cfg(feature = "matrix")]
+            layouts: [Layout::NORMAL; N],
+            intensities: [0; N],
+            scan_limits: [self.settings.scan_limit; N],
+            powered: [false; N],
+            inverted: [false; N],
+            #[cfg(feature = "segment")]
+            polarities: [SegmentPolarity::Normal; N],
+            #[cfg(feature = "segment")]
+            overflow: OverflowBehavior::Error,
+            #[cfg(feature = "segment")]
+            indicators: Indicators::DECIMAL_POINTS,
+            #[cfg(feature = "matrix")]
+            buffered: false,
+            sent: [[0; MAX_DIGITS]; N],
+            settings: self.settings,
+        })
+    }
+
+    ///
+    /// Builds a MAX7219 driver instance from DATA, CS and SCK pins,
+    /// see `MAX7219::from_pins`.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned for the same reasons as `build`
+    ///
+    pub fn build_pins<DATA, CS, SCK>(
+        self,
+        data: DATA,
+        cs: CS,
+        sck: SCK,
+    ) -> Result<MAX7219<PinConnector<DATA, CS, SCK>>, DataError>
+    where
+        DATA: OutputPin,
+        CS: OutputPin,
+        SCK: OutputPin,
+    {
+        self.build(PinConnector::new(data, cs, sck))
+    }
+
+    ///
+    /// Builds a MAX7219 driver instance from SPI in full hardware mode,
+    /// see `MAX7219::from_spi`.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned for the same reasons as `build`
+    ///
+    pub fn build_spi<SPI>(self, spi: SPI) -> Result<MAX7219<SpiConnector<SPI>>, DataError>
+    where
+        SPI: Write<u8>,
+    {
+        self.build(SpiConnector::new(spi))
+    }
+
+    ///
+    /// Builds a MAX7219 driver instance from SPI and a manually controlled CS pin,
+    /// see `MAX7219::from_spi_cs`.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned for the same reasons as `build`
+    ///
+    pub fn build_spi_cs<SPI, CS>(
+        self,
+        spi: SPI,
+        cs: CS,
+    ) -> Result<MAX7219<SpiConnectorSW<SPI, CS>>, DataError>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+    {
+        self.build(SpiConnectorSW::new(spi, cs))
+    }
+}
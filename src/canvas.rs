@@ -0,0 +1,217 @@
+//! Fixed size canvas over a chain of 8x8 LED matrices
+//!
+//! `Matrix` encodes the width and height of the canvas in its type, which
+//! are checked against the number of connected displays once when it is
+//! constructed. Pixel writes then only need to check the position against
+//! the type, matrices being wired from the top left row by row as described
+//! by the `ChainLayout`.
+
+use crate::connectors::Connector;
+use crate::{DataError, MAX7219, MAX_DIGITS, MAX_DISPLAYS};
+
+///
+/// Order in which the matrices of a canvas are wired, starting with
+/// display 0 (last in series) at the top left
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainLayout {
+    /// Every row of matrices is wired left to right
+    RowMajor,
+    /// Rows of matrices alternate direction, every second row being wired
+    /// right to left. Matrices mounted upside down in those rows also need
+    /// `MAX7219::set_orientation`.
+    Serpentine,
+}
+
+///
+/// Canvas `COLS` pixels wide and `ROWS` pixels high over a chain of matrices,
+/// both multiples of 8. Display 0 (last in series) is the top left matrix,
+/// followed by the rest of the top row of matrices and then the rows below
+/// in the order given by the `ChainLayout`, `RowMajor` unless set.
+/// The orientation and flips set on each display are applied to every pixel.
+///
+pub struct Matrix<
+    CONNECTOR,
+    const COLS: usize,
+    const ROWS: usize = 8,
+    const N: usize = MAX_DISPLAYS,
+> {
+    display: MAX7219<CONNECTOR, N>,
+    layout: ChainLayout,
+}
+
+impl<CONNECTOR, const COLS: usize, const ROWS: usize, const N: usize>
+    Matrix<CONNECTOR, COLS, ROWS, N>
+where
+    CONNECTOR: Connector,
+{
+    /// Width of the canvas in pixels
+    pub const WIDTH: usize = COLS;
+
+    /// Height of the canvas in pixels
+    pub const HEIGHT: usize = ROWS;
+
+    ///
+    /// Construct a new canvas from an initialized MAX7219 driver
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `COLS` or `ROWS` is not a multiple
+    ///   of 8 or the canvas does not have exactly one matrix per connected display
+    ///
+    pub fn new(display: MAX7219<CONNECTOR, N>) -> Result<Self, DataError> {
+        if COLS % MAX_DIGITS != 0
+            || ROWS % MAX_DIGITS != 0
+            || (COLS / MAX_DIGITS) * (ROWS / MAX_DIGITS) != display.devices
+        {
+            return Err(DataError::InvalidDisplayCount);
+        }
+
+        Ok(Matrix {
+            display,
+            layout: ChainLayout::RowMajor,
+        })
+    }
+
+    ///
+    /// Construct and initialize a new driver for a single row of `devices`
+    /// matrices, which requires `COLS` to be `8 * devices` and `ROWS` to be 8
+    ///
+    /// # Arguments
+    ///
+    /// * `devices` - number of displays connected in series, 1 to `N`
+    /// * `connector` - the interface used to send data to the displays
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `devices` is 0, exceeds `N`
+    ///   or does not match the canvas dimensions
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn from_chain(devices: usize, connector: CONNECTOR) -> Result<Self, DataError> {
+        if COLS != devices * MAX_DIGITS || ROWS != MAX_DIGITS {
+            return Err(DataError::InvalidDisplayCount);
+        }
+
+        Matrix::new(MAX7219::from_connector(devices, connector)?)
+    }
+
+    ///
+    /// Sets the order in which the matrices are wired, applied by all following pixel writes
+    ///
+    pub fn set_layout(&mut self, layout: ChainLayout) {
+        self.layout = layout;
+    }
+
+    ///
+    /// Turns a single pixel of the canvas on or off, keeping
+    /// the rest of its row as it was last written
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - the column of the pixel, `0` to `COLS - 1`
+    /// * `y` - the row of the pixel, `0` to `ROWS - 1`
+    /// * `on` - whether to turn the pixel on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `x` or `y` is outside of the canvas
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) -> Result<(), DataError> {
+        if x >= COLS || y >= ROWS {
+            return Err(DataError::InvalidPosition);
+        }
+
+        let (row, col) = (y / MAX_DIGITS, x / MAX_DIGITS);
+        let col = match self.layout {
+            ChainLayout::Serpentine if row % 2 == 1 => COLS / MAX_DIGITS - 1 - col,
+            _ => col,
+        };
+        let addr = row * (COLS / MAX_DIGITS) + col;
+        self.display
+            .set_pixel(addr, (x % MAX_DIGITS) as u8, (y % MAX_DIGITS) as u8, on)
+    }
+
+    ///
+    /// Turns all pixels of the canvas off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn clear(&mut self) -> Result<(), DataError> {
+        self.display.clear_all()?;
+        Ok(())
+    }
+
+    ///
+    /// Returns the wrapped driver, e.g. to set the intensity or start buffering
+    ///
+    pub fn display(&mut self) -> &mut MAX7219<CONNECTOR, N> {
+        &mut self.display
+    }
+
+    ///
+    /// Destroys the canvas returning the wrapped driver
+    ///
+    pub fn release(self) -> MAX7219<CONNECTOR, N> {
+        self.display
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingConnector;
+    use crate::test_util::display;
+
+    type Square = Matrix<RecordingConnector, 16, 16>;
+
+    // connects a 2x2 canvas wired in given layout, forgetting the initialization frames
+    fn square(layout: ChainLayout) -> Square {
+        let mut canvas: Square = Matrix::new(display(4)).unwrap();
+        canvas.set_layout(layout);
+        canvas
+    }
+
+    // lights given pixel, returning the display written to
+    fn light(canvas: &mut Square, x: usize, y: usize) -> usize {
+        canvas.set_pixel(x, y, true).unwrap();
+        let (addr, _, _) = canvas.display().connector().commands().last().unwrap();
+        addr
+    }
+
+    #[test]
+    fn maps_square_serpentine_chain() {
+        let mut canvas = square(ChainLayout::Serpentine);
+
+        assert_eq!(light(&mut canvas, 0, 0), 0);
+        assert_eq!(light(&mut canvas, 15, 0), 1);
+        assert_eq!(light(&mut canvas, 15, 15), 2);
+        assert_eq!(light(&mut canvas, 0, 15), 3);
+        assert_eq!(light(&mut canvas, 8, 8), 2);
+
+        // within each matrix the pixel is not mirrored
+        let commands = canvas.display().connector().commands();
+        assert!(commands.eq([
+            (0, 0x01, 0x80),
+            (1, 0x01, 0x01),
+            (2, 0x08, 0x01),
+            (3, 0x08, 0x80),
+            (2, 0x01, 0x80),
+        ]
+        .iter()
+        .copied()));
+    }
+
+    #[test]
+    fn maps_square_row_major_chain() {
+        let mut canvas = square(ChainLayout::RowMajor);
+
+        assert_eq!(light(&mut canvas, 0, 0), 0);
+        assert_eq!(light(&mut canvas, 15, 0), 1);
+        assert_eq!(light(&mut canvas, 0, 15), 2);
+        assert_eq!(light(&mut canvas, 15, 15), 3);
+    }
+}
@@ -1,59 +1,82 @@
 extern crate embedded_hal;
 
+use core::cell::RefCell;
+
+use embedded_hal::blocking::delay::DelayUs;
 use embedded_hal::blocking::spi::Write;
 use embedded_hal::digital::v2::OutputPin;
 
-use crate::{Command, DataError, MAX_DISPLAYS};
+use crate::DataError;
 
-/// Describes the interface used to connect to the MX7219
+/// Describes the interface used to connect to the MAX7219
+///
+/// All high level `MAX7219` commands are built on top of this trait, so
+/// the bit-banged and SPI transports share the same command logic.
+///
 pub trait Connector {
-    fn devices(&self) -> usize;
-
     ///
-    /// Writes data to given register as described by command
+    /// Shifts a complete chain frame out to the displays in a single
+    /// CS (LOAD) cycle so that every display latches its part at once
     ///
     /// # Arguments
     ///
-    /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `command` - the command/register on the display to write to
-    /// * `data` - the data byte value to write
+    /// * `frame` - header and data byte pairs, one pair per display
+    ///   in series (first pair -> last display)
     ///
     /// # Errors
     ///
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    fn write_data(&mut self, addr: usize, command: Command, data: u8) -> Result<(), DataError> {
-        self.write_raw(addr, command as u8, data)
-    }
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError>;
+}
 
-    ///
-    /// Writes data to given register as described by command
-    ///
-    /// # Arguments
-    ///
-    /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `header` - the command/register on the display to write to as u8
-    /// * `data` - the data byte value to write
-    ///
-    /// # Errors
-    ///
-    /// * `DataError` - returned in case there was an error during data transfer
-    ///
-    fn write_raw(&mut self, addr: usize, header: u8, data: u8) -> Result<(), DataError>;
+///
+/// Order in which the bits of each byte are shifted out by `PinConnector`.
+/// A standard MAX7219 expects `MsbFirst`, only change it for clones or
+/// wiring that reverses the bytes.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+///
+/// Idle level of the clock line driven by `PinConnector`. Data is set while
+/// the clock is at its idle level and latched by the chip on the rising edge,
+/// which is the first edge of each pulse for `IdleLow` and the second for `IdleHigh`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockPolarity {
+    IdleLow,
+    IdleHigh,
+}
+
+///
+/// Delay doing nothing, used by `PinConnector` unless
+/// a delay is given via `PinConnector::new_with_delay`.
+///
+pub struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
 }
 
 /// Direct GPIO pins connector
-pub struct PinConnector<DATA, CS, SCK>
+pub struct PinConnector<DATA, CS, SCK, DELAY = NoDelay>
 where
     DATA: OutputPin,
     CS: OutputPin,
     SCK: OutputPin,
+    DELAY: DelayUs<u32>,
 {
-    devices: usize,
-    buffer: [u8; MAX_DISPLAYS * 2],
     data: DATA,
     cs: CS,
     sck: SCK,
+    delay: DELAY,
+    pulse_us: u32,
+    bit_order: BitOrder,
+    polarity: ClockPolarity,
 }
 
 impl<DATA, CS, SCK> PinConnector<DATA, CS, SCK>
@@ -62,49 +85,127 @@ where
     CS: OutputPin,
     SCK: OutputPin,
 {
-    pub(crate) fn new(displays: usize, data: DATA, cs: CS, sck: SCK) -> Self {
+    ///
+    /// Construct a new bit-banged connector from DATA, CS and SCK pins
+    /// set to output mode.
+    ///
+    pub fn new(data: DATA, cs: CS, sck: SCK) -> Self {
+        PinConnector::new_with_delay(data, cs, sck, NoDelay, 0)
+    }
+
+    ///
+    /// Destroys the connector returning the DATA, CS and SCK pins
+    ///
+    pub fn release(self) -> (DATA, CS, SCK) {
+        (self.data, self.cs, self.sck)
+    }
+}
+
+impl<DATA, CS, SCK, DELAY> PinConnector<DATA, CS, SCK, DELAY>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    ///
+    /// Construct a new bit-banged connector from DATA, CS and SCK pins
+    /// set to output mode, waiting `pulse_us` after each clock edge.
+    /// Slows down the transfer for level shifters or long wires which
+    /// can not follow the pins toggled at full speed.
+    ///
+    pub fn new_with_delay(data: DATA, cs: CS, sck: SCK, delay: DELAY, pulse_us: u32) -> Self {
         PinConnector {
-            devices: displays,
-            buffer: [0; MAX_DISPLAYS * 2],
             data,
             cs,
             sck,
+            delay,
+            pulse_us,
+            bit_order: BitOrder::MsbFirst,
+            polarity: ClockPolarity::IdleLow,
+        }
+    }
+
+    ///
+    /// Destroys the connector returning the DATA, CS and SCK pins and the delay
+    ///
+    pub fn release_with_delay(self) -> (DATA, CS, SCK, DELAY) {
+        (self.data, self.cs, self.sck, self.delay)
+    }
+
+    ///
+    /// Sets the order in which bits are shifted out, MSB first by default
+    ///
+    pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    ///
+    /// Sets the idle level of the clock line, idle low by default
+    ///
+    pub fn clock_polarity(mut self, polarity: ClockPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    // shifts a single byte out in configured bit order
+    fn shift_out(&mut self, value: u8) -> Result<(), DataError> {
+        for i in 0..8 {
+            let bit = match self.bit_order {
+                BitOrder::MsbFirst => 7 - i,
+                BitOrder::LsbFirst => i,
+            };
+
+            if value & (1 << bit) > 0 {
+                self.data.set_high().map_err(|_| DataError::Pin)?;
+            } else {
+                self.data.set_low().map_err(|_| DataError::Pin)?;
+            }
+
+            self.clock(true)?;
+            self.pulse();
+            self.clock(false)?;
+            self.pulse();
+        }
+
+        Ok(())
+    }
+
+    // drives the clock away from (active) or back to its idle level
+    fn clock(&mut self, active: bool) -> Result<(), DataError> {
+        let high = match self.polarity {
+            ClockPolarity::IdleLow => active,
+            ClockPolarity::IdleHigh => !active,
+        };
+
+        if high {
+            self.sck.set_high().map_err(|_| DataError::Pin)
+        } else {
+            self.sck.set_low().map_err(|_| DataError::Pin)
+        }
+    }
+
+    // waits between clock edges if a pulse width was given
+    fn pulse(&mut self) {
+        if self.pulse_us > 0 {
+            self.delay.delay_us(self.pulse_us);
         }
     }
 }
 
-impl<DATA, CS, SCK> Connector for PinConnector<DATA, CS, SCK>
+impl<DATA, CS, SCK, DELAY> Connector for PinConnector<DATA, CS, SCK, DELAY>
 where
     DATA: OutputPin,
     CS: OutputPin,
     SCK: OutputPin,
+    DELAY: DelayUs<u32>,
 {
-    fn devices(&self) -> usize {
-        self.devices
-    }
-
-    fn write_raw(&mut self, addr: usize, header: u8, data: u8) -> Result<(), DataError> {
-        let offset = addr * 2;
-        let max_bytes = self.devices * 2;
-        self.buffer = [0; MAX_DISPLAYS * 2];
-
-        self.buffer[offset] = header;
-        self.buffer[offset + 1] = data;
-
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        self.clock(false)?; // start from idle level
         self.cs.set_low().map_err(|_| DataError::Pin)?;
-        for b in 0..max_bytes {
-            let value = self.buffer[b];
-
-            for i in 0..8 {
-                if value & (1 << (7 - i)) > 0 {
-                    self.data.set_high().map_err(|_| DataError::Pin)?;
-                } else {
-                    self.data.set_low().map_err(|_| DataError::Pin)?;
-                }
-
-                self.sck.set_high().map_err(|_| DataError::Pin)?;
-                self.sck.set_low().map_err(|_| DataError::Pin)?;
-            }
+        for b in frame {
+            self.shift_out(*b)?;
         }
         self.cs.set_high().map_err(|_| DataError::Pin)?;
 
@@ -112,26 +213,31 @@ where
     }
 }
 
+/// Hardware controlled CS connector with SPI transfer
 pub struct SpiConnector<SPI>
 where
     SPI: Write<u8>,
 {
-    devices: usize,
-    buffer: [u8; MAX_DISPLAYS * 2],
     spi: SPI,
 }
 
-/// Hardware controlled CS connector with SPI transfer
 impl<SPI> SpiConnector<SPI>
 where
     SPI: Write<u8>,
 {
-    pub(crate) fn new(displays: usize, spi: SPI) -> Self {
-        SpiConnector {
-            devices: displays,
-            buffer: [0; MAX_DISPLAYS * 2],
-            spi,
-        }
+    ///
+    /// Construct a new connector from an SPI interface which controls
+    /// the CS (LOAD) line in hardware.
+    ///
+    pub fn new(spi: SPI) -> Self {
+        SpiConnector { spi }
+    }
+
+    ///
+    /// Destroys the connector returning the SPI
+    ///
+    pub fn release(self) -> SPI {
+        self.spi
     }
 }
 
@@ -139,27 +245,18 @@ impl<SPI> Connector for SpiConnector<SPI>
 where
     SPI: Write<u8>,
 {
-    fn devices(&self) -> usize {
-        self.devices
-    }
-
-    fn write_raw(&mut self, addr: usize, header: u8, data: u8) -> Result<(), DataError> {
-        let offset = addr * 2;
-        let max_bytes = self.devices * 2;
-        self.buffer = [0; MAX_DISPLAYS * 2];
-
-        self.buffer[offset] = header;
-        self.buffer[offset + 1] = data;
-
-        self.spi
-            .write(&self.buffer[0..max_bytes])
-            .map_err(|_| DataError::Spi)?;
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        self.spi.write(frame).map_err(|_| DataError::Spi)?;
 
         Ok(())
     }
 }
 
-/// Software controlled CS connector with SPI transfer
+///
+/// Software controlled CS connector with SPI transfer. CS is raised
+/// only after the whole frame is written, which latches the data
+/// of all displays in the chain at once.
+///
 pub struct SpiConnectorSW<SPI, CS>
 where
     SPI: Write<u8>,
@@ -174,12 +271,23 @@ where
     SPI: Write<u8>,
     CS: OutputPin,
 {
-    pub(crate) fn new(displays: usize, spi: SPI, cs: CS) -> Self {
+    ///
+    /// Construct a new connector from an SPI interface and a CS pin
+    /// set to output mode which is toggled manually around each frame.
+    ///
+    pub fn new(spi: SPI, cs: CS) -> Self {
         SpiConnectorSW {
-            spi_c: SpiConnector::new(displays, spi),
+            spi_c: SpiConnector::new(spi),
             cs,
         }
     }
+
+    ///
+    /// Destroys the connector returning the SPI and CS pin
+    ///
+    pub fn release(self) -> (SPI, CS) {
+        (self.spi_c.release(), self.cs)
+    }
 }
 
 impl<SPI, CS> Connector for SpiConnectorSW<SPI, CS>
@@ -187,17 +295,176 @@ where
     SPI: Write<u8>,
     CS: OutputPin,
 {
-    fn devices(&self) -> usize {
-        self.spi_c.devices
-    }
-
-    fn write_raw(&mut self, addr: usize, header: u8, data: u8) -> Result<(), DataError> {
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
         self.cs.set_low().map_err(|_| DataError::Pin)?;
-        self.spi_c
-            .write_raw(addr, header, data)
-            .map_err(|_| DataError::Spi)?;
+        let written = self.spi_c.write_frame(frame);
+        // raise CS even if the transfer failed, so the next frame starts clean
         self.cs.set_high().map_err(|_| DataError::Pin)?;
 
-        Ok(())
+        written
+    }
+}
+
+///
+/// Output pin shared by several connectors, such as the DATA and SCK lines
+/// of chains which only have separate CS (LOAD) lines. Wrap the pin in a
+/// `RefCell` and hand a `SharedPin` to each `PinConnector`:
+///
+/// * the `RefCell` is only borrowed while a single level is set, so
+///   instances sharing it can be used one after the other freely
+/// * a `RefCell` can not be shared between threads or interrupt handlers,
+///   use one `MAX7219` instance per context or wrap the whole bus in a mutex
+///
+/// The MAX7219 shifts in data on every rising clock edge, yet only latches it when
+/// its own CS rises, so chains which are not addressed keep showing what they did.
+///
+pub struct SharedPin<'a, PIN>
+where
+    PIN: OutputPin,
+{
+    pin: &'a RefCell<PIN>,
+}
+
+impl<'a, PIN> SharedPin<'a, PIN>
+where
+    PIN: OutputPin,
+{
+    ///
+    /// Construct a new handle to the shared pin set to output mode
+    ///
+    pub fn new(pin: &'a RefCell<PIN>) -> Self {
+        SharedPin { pin }
+    }
+}
+
+impl<'a, PIN> OutputPin for SharedPin<'a, PIN>
+where
+    PIN: OutputPin,
+{
+    type Error = PIN::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.borrow_mut().set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.borrow_mut().set_high()
+    }
+}
+
+///
+/// SPI shared by several `SpiConnectorSW` connectors with separate CS pins,
+/// following the same borrowing rules as `SharedPin`. The SPI is borrowed
+/// for the whole frame only, while the CS of the connector is low.
+///
+pub struct SharedSpi<'a, SPI>
+where
+    SPI: Write<u8>,
+{
+    spi: &'a RefCell<SPI>,
+}
+
+impl<'a, SPI> SharedSpi<'a, SPI>
+where
+    SPI: Write<u8>,
+{
+    ///
+    /// Construct a new handle to the shared SPI
+    ///
+    pub fn new(spi: &'a RefCell<SPI>) -> Self {
+        SharedSpi { spi }
+    }
+}
+
+impl<'a, SPI> Write<u8> for SharedSpi<'a, SPI>
+where
+    SPI: Write<u8>,
+{
+    type Error = SPI::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.spi.borrow_mut().write(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::WireRecorder;
+    use crate::{Command, MAX7219};
+
+    #[test]
+    fn clock_edges_follow_polarity() {
+        for polarity in [ClockPolarity::IdleLow, ClockPolarity::IdleHigh].iter() {
+            let wire: WireRecorder = WireRecorder::new();
+            let connector = PinConnector::new(wire.data_pin(), wire.cs_pin(), wire.sck_pin())
+                .clock_polarity(*polarity);
+            let mut display: MAX7219<_> = MAX7219::from_connector(1, connector).unwrap();
+            wire.clear();
+
+            display.write_data(0, Command::Intensity, 0xA5).unwrap();
+
+            // as many rising as falling edges ending at the idle level means every
+            // pulse starts from the idle level, sampled on the first or second edge
+            assert_eq!(wire.rising_edges(), 16);
+            assert_eq!(wire.falling_edges(), 16);
+            assert_eq!(wire.sck_high(), *polarity == ClockPolarity::IdleHigh);
+            assert_eq!(&*wire.bytes(), &[0x0A, 0xA5]);
+        }
+    }
+
+    #[test]
+    fn pins_shift_whole_frames_latched_once() {
+        for polarity in [ClockPolarity::IdleLow, ClockPolarity::IdleHigh].iter() {
+            let wire: WireRecorder = WireRecorder::new();
+            let (data, cs, sck) = (wire.data_pin(), wire.cs_pin(), wire.sck_pin());
+            let mut display = match polarity {
+                ClockPolarity::IdleLow => MAX7219::from_pins(3, data, cs, sck),
+                ClockPolarity::IdleHigh => MAX7219::from_connector(
+                    3,
+                    PinConnector::new(data, cs, sck).clock_polarity(*polarity),
+                ),
+            }
+            .unwrap();
+            wire.clear();
+
+            // checks the frame latched last, 16 clock pulses per display
+            let latched = |frames: usize, frame: &[u8]| {
+                assert_eq!(wire.load_count(), frames);
+                assert_eq!(wire.rising_edges(), 16 * 3 * frames);
+                assert_eq!(wire.partial_bits(), 0);
+                assert_eq!(&wire.bytes()[(frames - 1) * 6..], frame);
+            };
+
+            display.write_data(1, Command::Intensity, 0x03).unwrap();
+            latched(1, &[0x00, 0x00, 0x0A, 0x03, 0x00, 0x00]);
+
+            display.write_data_all(Command::Power, 0x01).unwrap();
+            latched(2, &[0x0C, 0x01, 0x0C, 0x01, 0x0C, 0x01]);
+
+            let frame = [0x01, 0x81, 0x09, 0x00, 0x0B, 0x07];
+            display.write_chain(&frame).unwrap();
+            latched(3, &frame);
+        }
+    }
+
+    // SPI failing every transfer
+    struct FailingSpi;
+
+    impl Write<u8> for FailingSpi {
+        type Error = ();
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), Self::Error> {
+            Err(())
+        }
+    }
+
+    #[test]
+    fn software_cs_rises_after_failed_transfer() {
+        let wire: WireRecorder = WireRecorder::new();
+        let mut connector = SpiConnectorSW::new(FailingSpi, wire.cs_pin());
+
+        assert_eq!(connector.write_frame(&[0x0A, 0x01]), Err(DataError::Spi));
+        assert_eq!(wire.load_count(), 1);
     }
 }
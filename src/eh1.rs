@@ -0,0 +1,421 @@
+//! Connectors for `embedded-hal` 1.0 pins and SPI
+//!
+//! Enabled by the `eh1` feature. These mirror the connectors in
+//! `connectors` on top of the 1.0 traits, so HALs which already
+//! moved on can be used without a compatibility layer.
+//!
+//! There are two ways to drive the CS (LOAD) line over SPI:
+//!
+//! * `SpiConnector` takes an `SpiDevice`, leaving CS to the bus abstraction
+//!   which asserts it for each transaction, be it a GPIO or hardware CS.
+//! * `SpiConnectorSW` takes an exclusive `SpiBus` and a separate `OutputPin`
+//!   toggled by the driver around each frame.
+//!
+//! Either way the displays latch their data on the rising edge of CS, so it must
+//! only rise once the bytes of the whole chain are shifted out. Every frame is sent
+//! as a single transaction, and `SpiConnectorSW` flushes the bus before raising CS.
+
+use embedded_hal_1::delay::DelayNs;
+use embedded_hal_1::digital::{Error as _, OutputPin};
+use embedded_hal_1::spi::{Error as _, SpiBus, SpiDevice};
+
+use crate::connectors::{BitOrder, ClockPolarity, Connector, NoDelay};
+use crate::{DataError, MAX7219};
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Direct GPIO pins connector
+pub struct PinConnector<DATA, CS, SCK, DELAY = NoDelay>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+    DELAY: DelayNs,
+{
+    data: DATA,
+    cs: CS,
+    sck: SCK,
+    delay: DELAY,
+    pulse_us: u32,
+    bit_order: BitOrder,
+    polarity: ClockPolarity,
+}
+
+impl<DATA, CS, SCK> PinConnector<DATA, CS, SCK>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+{
+    ///
+    /// Construct a new bit-banged connector from DATA, CS and SCK pins
+    /// set to output mode.
+    ///
+    pub fn new(data: DATA, cs: CS, sck: SCK) -> Self {
+        PinConnector::new_with_delay(data, cs, sck, NoDelay, 0)
+    }
+
+    ///
+    /// Destroys the connector returning the DATA, CS and SCK pins
+    ///
+    pub fn release(self) -> (DATA, CS, SCK) {
+        (self.data, self.cs, self.sck)
+    }
+}
+
+impl<DATA, CS, SCK, DELAY> PinConnector<DATA, CS, SCK, DELAY>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+    DELAY: DelayNs,
+{
+    ///
+    /// Construct a new bit-banged connector from DATA, CS and SCK pins
+    /// set to output mode, waiting `pulse_us` after each clock edge,
+    /// see `connectors::PinConnector::new_with_delay`.
+    ///
+    pub fn new_with_delay(data: DATA, cs: CS, sck: SCK, delay: DELAY, pulse_us: u32) -> Self {
+        PinConnector {
+            data,
+            cs,
+            sck,
+            delay,
+            pulse_us,
+            bit_order: BitOrder::MsbFirst,
+            polarity: ClockPolarity::IdleLow,
+        }
+    }
+
+    ///
+    /// Destroys the connector returning the DATA, CS and SCK pins and the delay
+    ///
+    pub fn release_with_delay(self) -> (DATA, CS, SCK, DELAY) {
+        (self.data, self.cs, self.sck, self.delay)
+    }
+
+    ///
+    /// Sets the order in which bits are shifted out, MSB first by default
+    ///
+    pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    ///
+    /// Sets the idle level of the clock line, idle low by default
+    ///
+    pub fn clock_polarity(mut self, polarity: ClockPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    // shifts a single byte out in configured bit order
+    fn shift_out(&mut self, value: u8) -> Result<(), DataError> {
+        for i in 0..8 {
+            let bit = match self.bit_order {
+                BitOrder::MsbFirst => 7 - i,
+                BitOrder::LsbFirst => i,
+            };
+
+            if value & (1 << bit) > 0 {
+                self.data.set_high().map_err(|e| e.kind())?;
+            } else {
+                self.data.set_low().map_err(|e| e.kind())?;
+            }
+
+            self.clock(true)?;
+            self.pulse();
+            self.clock(false)?;
+            self.pulse();
+        }
+
+        Ok(())
+    }
+
+    // drives the clock away from (active) or back to its idle level
+    fn clock(&mut self, active: bool) -> Result<(), DataError> {
+        let high = match self.polarity {
+            ClockPolarity::IdleLow => active,
+            ClockPolarity::IdleHigh => !active,
+        };
+
+        if high {
+            self.sck.set_high().map_err(|e| e.kind())?;
+        } else {
+            self.sck.set_low().map_err(|e| e.kind())?;
+        }
+
+        Ok(())
+    }
+
+    // waits between clock edges if a pulse width was given
+    fn pulse(&mut self) {
+        if self.pulse_us > 0 {
+            self.delay.delay_us(self.pulse_us);
+        }
+    }
+}
+
+impl<DATA, CS, SCK, DELAY> Connector for PinConnector<DATA, CS, SCK, DELAY>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+    DELAY: DelayNs,
+{
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        self.clock(false)?; // start from idle level
+        self.cs.set_low().map_err(|e| e.kind())?;
+        for b in frame {
+            self.shift_out(*b)?;
+        }
+        self.cs.set_high().map_err(|e| e.kind())?;
+
+        Ok(())
+    }
+}
+
+/// SPI device connector, the device controls the CS (LOAD) line
+pub struct SpiConnector<SPI>
+where
+    SPI: SpiDevice,
+{
+    spi: SPI,
+}
+
+impl<SPI> SpiConnector<SPI>
+where
+    SPI: SpiDevice,
+{
+    ///
+    /// Construct a new connector from an SPI device which controls
+    /// the CS (LOAD) line for each transaction.
+    ///
+    pub fn new(spi: SPI) -> Self {
+        SpiConnector { spi }
+    }
+
+    ///
+    /// Destroys the connector returning the SPI
+    ///
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> Connector for SpiConnector<SPI>
+where
+    SPI: SpiDevice,
+{
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        self.spi.write(frame).map_err(|e| e.kind())?;
+
+        Ok(())
+    }
+}
+
+/// Software controlled CS connector with an exclusive SPI bus
+pub struct SpiConnectorSW<SPI, CS>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+{
+    spi: SPI,
+    cs: CS,
+}
+
+impl<SPI, CS> SpiConnectorSW<SPI, CS>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+{
+    ///
+    /// Construct a new connector from an SPI bus and a CS pin
+    /// set to output mode which is toggled manually around each frame.
+    ///
+    pub fn new(spi: SPI, cs: CS) -> Self {
+        SpiConnectorSW { spi, cs }
+    }
+
+    ///
+    /// Destroys the connector returning the SPI and CS pin
+    ///
+    pub fn release(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+}
+
+impl<SPI, CS> Connector for SpiConnectorSW<SPI, CS>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+{
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        self.cs.set_low().map_err(|e| e.kind())?;
+        // finish before latching, raising CS even if the transfer failed
+        // so the next frame starts clean
+        let written = self.spi.write(frame).and_then(|_| self.spi.flush());
+        self.cs.set_high().map_err(|e| e.kind())?;
+
+        written.map_err(|e| e.kind().into())
+    }
+}
+
+impl<DATA, CS, SCK> MAX7219<PinConnector<DATA, CS, SCK>>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+{
+    ///
+    /// Construct a new MAX7219 driver instance from `embedded-hal` 1.0
+    /// DATA, CS and SCK pins, see `MAX7219::from_pins`.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn from_pins_eh1(displays: usize, data: DATA, cs: CS, sck: SCK) -> Result<Self, DataError> {
+        MAX7219::from_connector(displays, PinConnector::new(data, cs, sck))
+    }
+}
+
+impl<DATA, CS, SCK, DELAY> MAX7219<PinConnector<DATA, CS, SCK, DELAY>>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+    DELAY: DelayNs,
+{
+    ///
+    /// Construct a new MAX7219 driver instance from `embedded-hal` 1.0 DATA, CS
+    /// and SCK pins waiting `pulse_us` after each clock edge, see
+    /// `MAX7219::from_pins_with_delay`.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn from_pins_with_delay_eh1(
+        displays: usize,
+        data: DATA,
+        cs: CS,
+        sck: SCK,
+        delay: DELAY,
+        pulse_us: u32,
+    ) -> Result<Self, DataError> {
+        let connector = PinConnector::new_with_delay(data, cs, sck, delay, pulse_us);
+
+        MAX7219::from_connector(displays, connector)
+    }
+}
+
+impl<SPI> MAX7219<SpiConnector<SPI>>
+where
+    SPI: SpiDevice,
+{
+    ///
+    /// Construct a new MAX7219 driver instance from an `embedded-hal` 1.0
+    /// SPI device controlling the CS (LOAD) line, see `MAX7219::from_spi`.
+    ///
+    /// * `NOTE` - make sure the SPI is initialized in MODE_0 with max 10 Mhz frequency.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn from_spi_eh1(displays: usize, spi: SPI) -> Result<Self, DataError> {
+        MAX7219::from_connector(displays, SpiConnector::new(spi))
+    }
+}
+
+impl<SPI, CS> MAX7219<SpiConnectorSW<SPI, CS>>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+{
+    ///
+    /// Construct a new MAX7219 driver instance from an `embedded-hal` 1.0
+    /// SPI bus and CS pin set to output, see `MAX7219::from_spi_cs`.
+    ///
+    /// * `NOTE` - make sure the SPI is initialized in MODE_0 with max 10 Mhz frequency.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn from_spi_cs_eh1(displays: usize, spi: SPI, cs: CS) -> Result<Self, DataError> {
+        MAX7219::from_connector(displays, SpiConnectorSW::new(spi, cs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::WireRecorder;
+    use crate::Command;
+
+    #[test]
+    fn clock_edges_follow_polarity() {
+        for polarity in [ClockPolarity::IdleLow, ClockPolarity::IdleHigh].iter() {
+            let wire: WireRecorder = WireRecorder::new();
+            let connector = PinConnector::new(wire.data_pin(), wire.cs_pin(), wire.sck_pin())
+                .clock_polarity(*polarity);
+            let mut display: MAX7219<_> = MAX7219::from_connector(1, connector).unwrap();
+            wire.clear();
+
+            display.write_data(0, Command::Intensity, 0xA5).unwrap();
+
+            // see the test of the embedded-hal 0.2 connector
+            assert_eq!(wire.rising_edges(), 16);
+            assert_eq!(wire.falling_edges(), 16);
+            assert_eq!(wire.sck_high(), *polarity == ClockPolarity::IdleHigh);
+            assert_eq!(&*wire.bytes(), &[0x0A, 0xA5]);
+        }
+    }
+
+    // SPI bus failing every transfer
+    struct FailingBus;
+
+    impl embedded_hal_1::spi::ErrorType for FailingBus {
+        type Error = embedded_hal_1::spi::ErrorKind;
+    }
+
+    impl SpiBus for FailingBus {
+        fn read(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+            Err(embedded_hal_1::spi::ErrorKind::Other)
+        }
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), Self::Error> {
+            Err(embedded_hal_1::spi::ErrorKind::Other)
+        }
+
+        fn transfer(&mut self, _read: &mut [u8], _write: &[u8]) -> Result<(), Self::Error> {
+            Err(embedded_hal_1::spi::ErrorKind::Other)
+        }
+
+        fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+            Err(embedded_hal_1::spi::ErrorKind::Other)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn software_cs_rises_after_failed_transfer() {
+        let wire: WireRecorder = WireRecorder::new();
+        let mut connector = SpiConnectorSW::new(FailingBus, wire.cs_pin());
+
+        assert_eq!(connector.write_frame(&[0x0A, 0x01]), Err(DataError::Spi));
+        assert_eq!(wire.load_count(), 1);
+    }
+}
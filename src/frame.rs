@@ -0,0 +1,148 @@
+//! Transport-free frame building shared by `MAX7219` and `MAX7219Async`
+//!
+//! Both drivers compute the header and data pairs to send and fill their chain
+//! buffer with these helpers, only differing in how the frames are shifted out.
+
+use core::iter::once;
+
+use crate::builder::Settings;
+use crate::{bcd_byte, ssb_byte, Command, DataError, Digit, MAX_DIGITS};
+
+/// Header and data pairs writing all digit registers of a display, in send order
+pub(crate) type DigitPairs = [[u8; 2]; MAX_DIGITS];
+
+/// Setting of a display changed by a header and data pair
+pub(crate) enum Setting {
+    DecodeMode(u8),
+    Intensity(u8),
+    ScanLimit(u8),
+    Power(bool),
+}
+
+impl Setting {
+    // the setting written by given pair, `None` for digits and other registers
+    pub(crate) fn sent(header: u8, data: u8) -> Option<Setting> {
+        if header == Command::DecodeMode as u8 {
+            Some(Setting::DecodeMode(data))
+        } else if header == Command::Intensity as u8 {
+            Some(Setting::Intensity(data & 0x0F))
+        } else if header == Command::ScanLimit as u8 {
+            Some(Setting::ScanLimit((data & 0x07) + 1))
+        } else if header == Command::Power as u8 {
+            Some(Setting::Power(data & 0x01 > 0))
+        } else {
+            None
+        }
+    }
+}
+
+// fails for addresses outside of a chain of `devices` displays
+pub(crate) fn check_addr(addr: usize, devices: usize) -> Result<(), DataError> {
+    if addr >= devices {
+        return Err(DataError::AddressOutOfRange(addr));
+    }
+
+    Ok(())
+}
+
+// fills the buffer to write a single register on given display and no-ops to the rest.
+// `last` is the display of the previous single register write still in the buffer,
+// in which case only that pair needs clearing.
+pub(crate) fn fill_register<const N: usize>(
+    buffer: &mut [[u8; 2]; N],
+    last: Option<usize>,
+    addr: usize,
+    header: u8,
+    data: u8,
+) {
+    match last {
+        Some(last) => buffer[last] = [0; 2],
+        None => *buffer = [[0; 2]; N],
+    }
+    buffer[addr] = [header, data];
+}
+
+// fills the buffer with one row of each frame, one frame per display
+#[cfg(any(feature = "matrix", feature = "async"))]
+pub(crate) fn fill_row<const N: usize>(
+    buffer: &mut [[u8; 2]; N],
+    row: usize,
+    frames: &[[u8; MAX_DIGITS]],
+) {
+    for (pair, frame) in buffer.iter_mut().zip(frames.iter()) {
+        *pair = [row as u8 + 1, frame[row]];
+    }
+}
+
+// fills the buffer to blank given digit on every display as decoded by it
+pub(crate) fn fill_blanks<const N: usize>(
+    buffer: &mut [[u8; 2]; N],
+    decode_masks: &[u8; N],
+    digit: Digit,
+) {
+    for (pair, mask) in buffer.iter_mut().zip(decode_masks.iter()) {
+        *pair = [digit.register(), blank_value(*mask, digit)];
+    }
+}
+
+// value showing given digit register as empty with given decode mask
+pub(crate) fn blank_value(decode_mask: u8, digit: Digit) -> u8 {
+    if decode_mask & (1 << digit.index()) > 0 {
+        0b0000_1111 // Code B blank
+    } else {
+        0x00
+    }
+}
+
+// digit pairs showing a byte string with dots from the segment table, leftmost first
+pub(crate) fn str_pairs(string: &[u8; MAX_DIGITS], dots: u8) -> DigitPairs {
+    let mut pairs = [[0; 2]; MAX_DIGITS];
+    let mut dot_product: u8 = 0b1000_0000;
+    let digits = (1..=MAX_DIGITS as u8).rev().zip(string.iter());
+    for (pair, (digit, b)) in pairs.iter_mut().zip(digits) {
+        let dot = (dots & dot_product) > 0;
+        dot_product >>= 1;
+        *pair = [digit, ssb_byte(*b, dot)];
+    }
+
+    pairs
+}
+
+// digit pairs showing a BCD string decoded as Code B, leftmost first
+pub(crate) fn bcd_pairs(bcd: &[u8; MAX_DIGITS]) -> DigitPairs {
+    let mut pairs = [[0; 2]; MAX_DIGITS];
+    let digits = (1..=MAX_DIGITS as u8).rev().zip(bcd.iter());
+    for (pair, (digit, b)) in pairs.iter_mut().zip(digits) {
+        *pair = [digit, bcd_byte(*b)];
+    }
+
+    pairs
+}
+
+// digit pairs writing raw register values, `Digit0` first
+pub(crate) fn raw_pairs(raw: &[u8; MAX_DIGITS]) -> DigitPairs {
+    let mut pairs = [[0; 2]; MAX_DIGITS];
+    for (pair, (digit, b)) in pairs.iter_mut().zip((1..).zip(raw.iter())) {
+        *pair = [digit, *b];
+    }
+
+    pairs
+}
+
+// commands of the initialization sequence sent before clearing the digits,
+// which is followed by `power_command`
+pub(crate) fn init_commands(settings: &Settings) -> impl Iterator<Item = (Command, u8)> {
+    let intensity = settings
+        .intensity
+        .map(|intensity| (Command::Intensity, intensity));
+
+    once((Command::DisplayTest, 0x00)) // turn testmode off
+        .chain(once((Command::ScanLimit, settings.scan_limit - 1)))
+        .chain(once((Command::DecodeMode, settings.decode_mode as u8)))
+        .chain(intensity)
+}
+
+// command ending the initialization sequence
+pub(crate) fn power_command(settings: &Settings) -> (Command, u8) {
+    (Command::Power, settings.power_on as u8)
+}
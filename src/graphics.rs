@@ -0,0 +1,142 @@
+//! [`embedded-graphics`] support for chains of 8x8 LED matrices
+//!
+//! Enabled with the `graphics` feature. The chain is treated as a single
+//! canvas `8 * devices` pixels wide and 8 pixels high, where columns
+//! `0..8` belong to display 0 (last in series), `8..16` to display 1 and so on.
+//! The orientation and flips set on each display are applied when flushing.
+//! Colors are `BinaryColor` as the chip can not dim single pixels.
+//!
+//! [`embedded-graphics`]: https://docs.rs/embedded-graphics
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::BinaryColor;
+use embedded_graphics_core::Pixel;
+
+use crate::connectors::Connector;
+use crate::{DataError, MAX7219, MAX_DIGITS, MAX_DISPLAYS};
+
+///
+/// Framebuffer backed `DrawTarget` wrapping a `MAX7219` driving 8x8
+/// matrices. Drawing only changes the framebuffer, call `flush` to
+/// send the changed rows to the displays.
+///
+pub struct MatrixGraphics<CONNECTOR, const N: usize = MAX_DISPLAYS> {
+    display: MAX7219<CONNECTOR, N>,
+    frame: [[u8; MAX_DIGITS]; N],
+    dirty: [u8; N],
+}
+
+impl<CONNECTOR, const N: usize> MatrixGraphics<CONNECTOR, N>
+where
+    CONNECTOR: Connector,
+{
+    ///
+    /// Construct a new graphics target from an initialized MAX7219 driver
+    /// with an empty framebuffer
+    ///
+    pub fn new(display: MAX7219<CONNECTOR, N>) -> Self {
+        MatrixGraphics {
+            display,
+            frame: [[0; MAX_DIGITS]; N],
+            dirty: [0xFF; N],
+        }
+    }
+
+    ///
+    /// Writes all rows changed since last flush to the displays, one
+    /// transfer per changed row across the whole chain
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn flush(&mut self) -> Result<(), DataError> {
+        let devices = self.display.devices;
+
+        // rotated or flipped matrices map a changed row to other physical rows
+        let mut frames = [[0; MAX_DIGITS]; N];
+        let mut dirty = [0; N];
+        for addr in 0..devices {
+            let layout = self.display.layouts[addr];
+            frames[addr] = layout.frame(&self.frame[addr]);
+            dirty[addr] = if layout.keeps_rows() || self.dirty[addr] == 0 {
+                self.dirty[addr]
+            } else {
+                0xFF
+            };
+        }
+
+        for row in 0..MAX_DIGITS {
+            let mask = 1 << row;
+            if dirty[..devices].iter().all(|d| d & mask == 0) {
+                continue;
+            }
+
+            let rows = frames.iter().zip(dirty.iter());
+            for (pair, (frame, d)) in self.display.buffer[..devices].iter_mut().zip(rows) {
+                *pair = if d & mask > 0 {
+                    [row as u8 + 1, frame[row]]
+                } else {
+                    [0, 0]
+                };
+            }
+
+            self.display.write_buffer()?;
+        }
+
+        self.dirty = [0; N];
+
+        Ok(())
+    }
+
+    ///
+    /// Returns the wrapped driver, dropping the framebuffer
+    ///
+    pub fn release(self) -> MAX7219<CONNECTOR, N> {
+        self.display
+    }
+}
+
+impl<CONNECTOR, const N: usize> OriginDimensions for MatrixGraphics<CONNECTOR, N> {
+    fn size(&self) -> Size {
+        Size::new(
+            (self.display.devices * MAX_DIGITS) as u32,
+            MAX_DIGITS as u32,
+        )
+    }
+}
+
+impl<CONNECTOR, const N: usize> DrawTarget for MatrixGraphics<CONNECTOR, N> {
+    type Color = BinaryColor;
+    type Error = DataError;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let width = self.display.devices * MAX_DIGITS;
+
+        for Pixel(point, color) in pixels {
+            if point.x < 0
+                || point.y < 0
+                || point.x as usize >= width
+                || point.y as usize >= MAX_DIGITS
+            {
+                continue; // outside of the canvas
+            }
+
+            let (x, y) = (point.x as usize, point.y as usize);
+            let (addr, mask) = (x / MAX_DIGITS, 0b1000_0000 >> (x % MAX_DIGITS));
+            let row = &mut self.frame[addr][y];
+
+            match color {
+                BinaryColor::On => *row |= mask,
+                BinaryColor::Off => *row &= !mask,
+            }
+            self.dirty[addr] |= 1 << y;
+        }
+
+        Ok(())
+    }
+}
@@ -8,22 +8,97 @@
 #![deny(warnings)]
 #![no_std]
 
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "graphics")]
+extern crate embedded_graphics_core;
 extern crate embedded_hal;
+#[cfg(feature = "eh1")]
+extern crate embedded_hal_1;
+#[cfg(feature = "async")]
+extern crate embedded_hal_async;
 
+use core::convert::Infallible;
+use core::ops::Range;
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
 use embedded_hal::blocking::spi::Write;
 use embedded_hal::digital::v2::OutputPin;
 
 pub mod connectors;
 use connectors::*;
 
-/// Maximum number of displays connected in series supported by this lib.
-const MAX_DISPLAYS: usize = 8;
+pub mod builder;
+pub use builder::MAX7219Builder;
+use builder::Settings;
+
+mod frame;
+use frame::Setting;
+
+pub mod animation;
+#[cfg(feature = "matrix")]
+pub mod canvas;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+#[cfg(feature = "matrix")]
+use matrix::Layout;
+#[cfg(feature = "matrix")]
+pub mod scroll;
+#[cfg(feature = "segment")]
+pub mod segment;
+#[cfg(feature = "segment")]
+use segment::{Indicators, OverflowBehavior, SegmentPolarity};
+
+#[cfg(feature = "async")]
+pub mod asynch;
+#[cfg(feature = "eh1")]
+pub mod eh1;
+#[cfg(feature = "font")]
+pub mod font;
+#[cfg(feature = "graphics")]
+pub mod graphics;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+#[cfg(test)]
+pub(crate) mod test_util;
 
-/// Digits per display
-const MAX_DIGITS: usize = 8;
+/// Maximum number of displays connected in series supported by default,
+/// see `MAX7219::from_connector` for longer chains.
+pub const MAX_DISPLAYS: usize = 8;
+
+/// Digits per display, which are the rows of a matrix
+pub const MAX_DIGITS: usize = 8;
+
+/// Highest intensity level supported by the chip
+const MAX_INTENSITY: u8 = 0x0F;
+
+///
+/// Maps perceptual brightness levels `0` to `255` onto the 16 intensity steps
+/// of the chip using a gamma of 2.2, see `set_intensity_perceptual`.
+///
+#[rustfmt::skip]
+pub const GAMMA16: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    4, 4, 4, 4, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5,
+    5, 5, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 8, 8, 8, 8, 8,
+    8, 8, 8, 8, 8, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9,
+    10, 10, 10, 10, 10, 10, 10, 10, 10, 11, 11, 11, 11, 11, 11, 11,
+    11, 11, 12, 12, 12, 12, 12, 12, 12, 12, 12, 13, 13, 13, 13, 13,
+    13, 13, 13, 13, 14, 14, 14, 14, 14, 14, 14, 14, 15, 15, 15, 15,
+];
 
 /// Possible command register values on the display chip.
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Command {
     Noop = 0x00,
     Digit0 = 0x01,
@@ -41,25 +116,243 @@ pub enum Command {
     DisplayTest = 0x0F,
 }
 
-/// Decode modes for BCD encoded input.
-#[derive(Copy, Clone)]
+/// Decode modes for BCD encoded input, Code B decoding the lowest digits.
+/// Use `set_decode_mask` to decode any other combination of digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DecodeMode {
     NoDecode = 0x00,
     CodeBDigit0 = 0x01,
+    CodeBDigits1_0 = 0x03,
+    CodeBDigits2_0 = 0x07,
     CodeBDigits3_0 = 0x0F,
+    CodeBDigits4_0 = 0x1F,
+    CodeBDigits5_0 = 0x3F,
+    CodeBDigits6_0 = 0x7F,
     CodeBDigits7_0 = 0xFF,
 }
 
+impl Command {
+    ///
+    /// Parses a register address as sent to the chip back into a command,
+    /// `None` for addresses without a register
+    ///
+    pub fn from_u8(value: u8) -> Option<Command> {
+        match value {
+            0x00 => Some(Command::Noop),
+            0x01 => Some(Command::Digit0),
+            0x02 => Some(Command::Digit1),
+            0x03 => Some(Command::Digit2),
+            0x04 => Some(Command::Digit3),
+            0x05 => Some(Command::Digit4),
+            0x06 => Some(Command::Digit5),
+            0x07 => Some(Command::Digit6),
+            0x08 => Some(Command::Digit7),
+            0x09 => Some(Command::DecodeMode),
+            0x0A => Some(Command::Intensity),
+            0x0B => Some(Command::ScanLimit),
+            0x0C => Some(Command::Power),
+            0x0F => Some(Command::DisplayTest),
+            _ => None,
+        }
+    }
+}
+
+impl DecodeMode {
+    ///
+    /// Parses a decode mode register value back into a decode mode,
+    /// `None` for masks other than the ones listed by `DecodeMode`
+    ///
+    pub fn from_u8(value: u8) -> Option<DecodeMode> {
+        match value {
+            0x00 => Some(DecodeMode::NoDecode),
+            0x01 => Some(DecodeMode::CodeBDigit0),
+            0x03 => Some(DecodeMode::CodeBDigits1_0),
+            0x07 => Some(DecodeMode::CodeBDigits2_0),
+            0x0F => Some(DecodeMode::CodeBDigits3_0),
+            0x1F => Some(DecodeMode::CodeBDigits4_0),
+            0x3F => Some(DecodeMode::CodeBDigits5_0),
+            0x7F => Some(DecodeMode::CodeBDigits6_0),
+            0xFF => Some(DecodeMode::CodeBDigits7_0),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Digit of a display, `0` being `Digit0` which is the rightmost digit of
+/// 7-segment displays and row 0 of matrices. Maps to the `0x01` to `0x08`
+/// digit registers of the chip, so callers do not need to offset indexes.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Digit(u8);
+
+impl Digit {
+    ///
+    /// Construct a digit from its index, `None` if it is not within `0` to `7`
+    ///
+    pub fn new(index: u8) -> Option<Digit> {
+        if index as usize >= MAX_DIGITS {
+            return None;
+        }
+
+        Some(Digit(index))
+    }
+
+    ///
+    /// Returns all digits from `Digit0` to `Digit7`
+    ///
+    pub fn all() -> impl Iterator<Item = Digit> {
+        (0..MAX_DIGITS as u8).map(Digit)
+    }
+
+    ///
+    /// Returns the index of the digit, `0` to `7`
+    ///
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    ///
+    /// Returns the register address of the digit, `0x01` to `0x08`
+    ///
+    pub fn register(self) -> u8 {
+        self.0 + 1
+    }
+
+    ///
+    /// Returns the command writing to the digit register
+    ///
+    pub fn command(self) -> Command {
+        match self.0 {
+            0 => Command::Digit0,
+            1 => Command::Digit1,
+            2 => Command::Digit2,
+            3 => Command::Digit3,
+            4 => Command::Digit4,
+            5 => Command::Digit5,
+            6 => Command::Digit6,
+            _ => Command::Digit7,
+        }
+    }
+}
+
 ///
 /// Error raised in case there was an error
-/// during communication with the MAX7219 chip.
+/// during communication with the MAX7219 chip
+/// or given arguments can not be sent to it.
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DataError {
     /// An error occurred when working with SPI
     Spi,
     /// An error occurred when working with a PIN
     Pin,
+    /// The number of displays is zero or exceeds the supported capacity
+    InvalidDisplayCount,
+    /// The given display address is not lower than the number of connected displays
+    AddressOutOfRange(usize),
+    /// The given intensity is above the maximum of `0x0F`
+    InvalidIntensity(u8),
+    /// The number of scanned digits is not within `1` to `8`
+    InvalidScanLimit,
+    /// A row, column or digit position is outside of the display
+    InvalidPosition,
+    /// The length of given input does not match what is expected
+    InvalidLength,
+    /// The value does not fit on the display
+    Overflow,
+    /// The digit value is not within `0` to `9`
+    InvalidDigit,
+    /// The hours or minutes are not a valid time of day
+    InvalidTime,
+    /// The byte has no Code B representation or segment pattern
+    InvalidCharacter(u8),
+    /// The lower bound of a range is above its upper bound
+    InvalidRange,
+}
+
+impl From<Infallible> for DataError {
+    fn from(e: Infallible) -> Self {
+        match e {}
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl From<embedded_hal_1::digital::ErrorKind> for DataError {
+    fn from(_: embedded_hal_1::digital::ErrorKind) -> Self {
+        DataError::Pin
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl From<embedded_hal_1::spi::ErrorKind> for DataError {
+    fn from(_: embedded_hal_1::spi::ErrorKind) -> Self {
+        DataError::Spi
+    }
+}
+
+#[cfg(all(feature = "async", not(feature = "eh1")))]
+impl From<embedded_hal_async::spi::ErrorKind> for DataError {
+    fn from(_: embedded_hal_async::spi::ErrorKind) -> Self {
+        DataError::Spi
+    }
+}
+
+///
+/// Error raised by operations writing to displays one by one,
+/// telling which display was being written when it failed.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceError {
+    /// The display as connected in series (0 -> last) being written
+    pub addr: usize,
+    /// The command being written
+    pub command: Command,
+    /// The underlying error
+    pub error: DataError,
+}
+
+impl From<DeviceError> for DataError {
+    fn from(e: DeviceError) -> Self {
+        e.error
+    }
+}
+
+///
+/// Settings of every display in the chain as last set by the driver,
+/// see `MAX7219::snapshot` and `MAX7219::restore`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayState<const N: usize = MAX_DISPLAYS> {
+    /// Intensity of each display, `0x00` to `0x0F`
+    pub intensities: [u8; N],
+    /// Digits decoded as Code B on each display, see `MAX7219::set_decode_mask`
+    pub decode_masks: [u8; N],
+    /// Number of digits scanned on each display, `1` to `8`
+    pub scan_limits: [u8; N],
+    /// Whether each display is powered on
+    pub powered: [bool; N],
+}
+
+///
+/// Whether a display is lit, see `MAX7219::set_state`. At intensity `0x00` a
+/// display is still powered and scanning, which leaves a faint glow on some
+/// panels, while `Off` shuts it down entirely.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerState {
+    /// Shut down, keeping the digit registers
+    Off,
+    /// Powered at most at given intensity, lowering the intensity of brighter displays
+    /// while leaving dimmer ones as they are. `Dimmed(0x00)` is as dim as it gets while lit.
+    Dimmed(u8),
+    /// Powered at given intensity
+    On(u8),
 }
 
 ///
@@ -69,233 +362,1193 @@ pub enum DataError {
 /// a single connection. The actual connection interface
 /// is selected via constructor functions.
 ///
-pub struct MAX7219<CONNECTOR> {
+/// `N` is the maximum number of displays supported by this
+/// instance which sizes the internal buffer, `MAX_DISPLAYS` by default.
+///
+/// Power, intensity, scan limit, decode and clearing methods return the
+/// driver to chain setup steps, e.g. `display.power_on()?.clear_all()?`.
+///
+pub struct MAX7219<CONNECTOR, const N: usize = MAX_DISPLAYS> {
     c: CONNECTOR,
-    decode_mode: DecodeMode,
+    devices: usize,
+    buffer: [[u8; 2]; N],
+    single: Option<usize>,
+    digits: [[u8; MAX_DIGITS]; N],
+    decode_masks: [u8; N],
+    #[cfg(feature = "matrix")]
+    layouts: [Layout; N],
+    intensities: [u8; N],
+    scan_limits: [u8; N],
+    powered: [bool; N],
+    inverted: [bool; N],
+    #[cfg(feature = "segment")]
+    polarities: [SegmentPolarity; N],
+    #[cfg(feature = "segment")]
+    overflow: OverflowBehavior,
+    #[cfg(feature = "segment")]
+    indicators: Indicators,
+    #[cfg(feature = "matrix")]
+    buffered: bool,
+    sent: [[u8; MAX_DIGITS]; N],
+    settings: Settings,
 }
 
-impl<CONNECTOR> MAX7219<CONNECTOR>
+impl<CONNECTOR, const N: usize> MAX7219<CONNECTOR, N>
 where
     CONNECTOR: Connector,
 {
     ///
-    /// Powers on all connected displays
+    /// Powers on all connected displays
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn power_on(&mut self) -> Result<&mut Self, DataError> {
+        self.write_data_all(Command::Power, 0x01)?;
+        Ok(self)
+    }
+
+    ///
+    /// Powers off all connected displays
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn power_off(&mut self) -> Result<&mut Self, DataError> {
+        self.write_data_all(Command::Power, 0x00)?;
+        Ok(self)
+    }
+
+    ///
+    /// Powers on given display
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn power_on_device(&mut self, addr: usize) -> Result<&mut Self, DataError> {
+        self.write_data(addr, Command::Power, 0x01)?;
+        Ok(self)
+    }
+
+    ///
+    /// Powers off given display
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn power_off_device(&mut self, addr: usize) -> Result<&mut Self, DataError> {
+        self.write_data(addr, Command::Power, 0x00)?;
+        Ok(self)
+    }
+
+    ///
+    /// Powers given display on or off, returning whether it was powered on before
+    /// as last set by the driver
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `on` - whether to power the display on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_power(&mut self, addr: usize, on: bool) -> Result<bool, DataError> {
+        self.check_addr(addr)?;
+
+        let was_on = self.powered[addr];
+        if on {
+            self.power_on_device(addr)?;
+        } else {
+            self.power_off_device(addr)?;
+        }
+
+        Ok(was_on)
+    }
+
+    ///
+    /// Dims given display to the lowest intensity while keeping it powered and
+    /// scanning, unlike `power_off_device`. Some panels still glow faintly.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn dim_off(&mut self, addr: usize) -> Result<&mut Self, DataError> {
+        self.set_intensity(addr, 0x00)
+    }
+
+    ///
+    /// Powers given display off or sets its intensity and powers it on.
+    /// `PowerState::Dimmed` never raises the intensity of the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `state` - the state to put the display in
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if the intensity is above `0x0F`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_state(&mut self, addr: usize, state: PowerState) -> Result<&mut Self, DataError> {
+        let intensity = match state {
+            PowerState::Off => return self.power_off_device(addr),
+            PowerState::Dimmed(intensity) => {
+                check_intensity(intensity)?;
+                self.check_addr(addr)?;
+                intensity.min(self.intensities[addr])
+            }
+            PowerState::On(intensity) => intensity,
+        };
+
+        // change the intensity first so a display being powered on does not flash
+        self.set_intensity(addr, intensity)?;
+        if !self.powered[addr] {
+            self.power_on_device(addr)?;
+        }
+        Ok(self)
+    }
+
+    ///
+    /// Blinks given display by powering it off and on again `times` times,
+    /// leaving it powered on
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `times` - how many times to blink
+    /// * `on_ms` - milliseconds to stay on after each blink
+    /// * `off_ms` - milliseconds to stay off during each blink
+    /// * `delay` - the delay used for timing
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn blink<DELAY>(
+        &mut self,
+        addr: usize,
+        times: u8,
+        on_ms: u32,
+        off_ms: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), DataError>
+    where
+        DELAY: DelayMs<u32>,
+    {
+        self.check_addr(addr)?;
+
+        for _ in 0..times {
+            self.power_off_device(addr)?;
+            delay.delay_ms(off_ms);
+            self.power_on_device(addr)?;
+            delay.delay_ms(on_ms);
+        }
+
+        self.power_on_device(addr)?;
+        Ok(())
+    }
+
+    ///
+    /// Blinks all connected displays at once, see `blink`
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - how many times to blink
+    /// * `on_ms` - milliseconds to stay on after each blink
+    /// * `off_ms` - milliseconds to stay off during each blink
+    /// * `delay` - the delay used for timing
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn blink_all<DELAY>(
+        &mut self,
+        times: u8,
+        on_ms: u32,
+        off_ms: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), DataError>
+    where
+        DELAY: DelayMs<u32>,
+    {
+        for _ in 0..times {
+            self.power_off()?;
+            delay.delay_ms(off_ms);
+            self.power_on()?;
+            delay.delay_ms(on_ms);
+        }
+
+        self.power_on()?;
+        Ok(())
+    }
+
+    ///
+    /// Clears display by settings all digits to empty
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn clear_display(&mut self, addr: usize) -> Result<&mut Self, DataError> {
+        self.check_addr(addr)?;
+
+        for digit in Digit::all() {
+            self.write_register(addr, digit.register(), self.blank_value(addr, digit))?;
+        }
+
+        Ok(self)
+    }
+
+    ///
+    /// Blanks given digits leaving the others as they are. Digits decoded
+    /// as Code B are set to the Code B blank, others to all segments off.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `range` - the digits to blank, `0` (`Digit0`) being the rightmost one
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `range` is not within `0..8`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn blank_digits(&mut self, addr: usize, range: Range<u8>) -> Result<&mut Self, DataError> {
+        if range.start > range.end || range.end as usize > MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+        self.check_addr(addr)?;
+
+        for digit in range.filter_map(Digit::new) {
+            self.write_register(addr, digit.register(), self.blank_value(addr, digit))?;
+        }
+
+        Ok(self)
+    }
+
+    ///
+    /// Clears all connected displays by setting all digits to empty
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn clear_all(&mut self) -> Result<&mut Self, DataError> {
+        for digit in Digit::all() {
+            frame::fill_blanks(&mut self.buffer, &self.decode_masks, digit);
+            self.write_buffer()?;
+        }
+
+        Ok(self)
+    }
+
+    ///
+    /// Sets intensity level on the display. The chip has 16 brightness
+    /// steps from `0x00` (dimmest) to `0x0F` (brightest), which apply
+    /// to all of its digits or pixels alike. Even `0x00` keeps the display
+    /// lit, use `power_off_device` to turn it off.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `intensity` - intensity value to set to `0x00` to `0x0F`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if `intensity` is above `0x0F`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_intensity(&mut self, addr: usize, intensity: u8) -> Result<&mut Self, DataError> {
+        check_intensity(intensity)?;
+
+        self.write_data(addr, Command::Intensity, intensity)?;
+        Ok(self)
+    }
+
+    ///
+    /// Sets intensity level on all connected displays at once
+    ///
+    /// # Arguments
+    ///
+    /// * `intensity` - intensity value to set to `0x00` to `0x0F`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if `intensity` is above `0x0F`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_intensity_all(&mut self, intensity: u8) -> Result<&mut Self, DataError> {
+        check_intensity(intensity)?;
+
+        self.write_data_all(Command::Intensity, intensity)?;
+        Ok(self)
+    }
+
+    ///
+    /// Sets a different intensity level on each connected display in a single transfer
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - intensity value of each display as connected in series (0 -> last),
+    ///   `0x00` to `0x0F`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if there is not exactly one level per display
+    /// * `DataError::InvalidIntensity` - returned if any level is above `0x0F`,
+    ///   nothing is sent in that case
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_intensities(&mut self, levels: &[u8]) -> Result<&mut Self, DataError> {
+        if levels.len() != self.devices {
+            return Err(DataError::InvalidLength);
+        }
+        for intensity in levels {
+            check_intensity(*intensity)?;
+        }
+
+        for (pair, intensity) in self.buffer.iter_mut().zip(levels.iter()) {
+            *pair = [Command::Intensity as u8, *intensity];
+        }
+        self.write_buffer()?;
+        Ok(self)
+    }
+
+    ///
+    /// Sets intensity level on the display from a perceptual brightness, mapped
+    /// through `GAMMA16` so equal steps of `level` look like equal steps in brightness.
+    /// Note that the lowest intensity still lights the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `level` - the perceptual brightness, `0` (dimmest) to `255` (brightest)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_intensity_perceptual(
+        &mut self,
+        addr: usize,
+        level: u8,
+    ) -> Result<&mut Self, DataError> {
+        self.set_intensity(addr, GAMMA16[level as usize])
+    }
+
+    ///
+    /// Walks the intensity of given display one step at a time from the level
+    /// last set to `target`, waiting `step_ms` between steps. Displays start at
+    /// the lowest level unless initialized with an intensity.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `target` - intensity value to fade to `0x00` to `0x0F`
+    /// * `step_ms` - milliseconds to wait between steps
+    /// * `delay` - the delay used between steps
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidIntensity` - returned if `target` is above `0x0F`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn fade_to<DELAY>(
+        &mut self,
+        addr: usize,
+        target: u8,
+        step_ms: u32,
+        delay: &mut DELAY,
+    ) -> Result<(), DataError>
+    where
+        DELAY: DelayMs<u32>,
+    {
+        check_intensity(target)?;
+        self.check_addr(addr)?;
+
+        let mut intensity = self.intensities[addr];
+        while intensity != target {
+            if intensity < target {
+                intensity += 1;
+            } else {
+                intensity -= 1;
+            }
+
+            self.set_intensity(addr, intensity)?;
+            if intensity != target {
+                delay.delay_ms(step_ms);
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Sets how many digits are scanned on the display. Scanning fewer digits
+    /// lights each of them longer, so they appear brighter. With 3 or fewer digits
+    /// the datasheet recommends lowering the segment current via RSET.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `digits` - number of digits to scan, `1` to `8`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidScanLimit` - returned if `digits` is not within `1` to `8`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_scan_limit(&mut self, addr: usize, digits: u8) -> Result<&mut Self, DataError> {
+        if digits == 0 || digits as usize > MAX_DIGITS {
+            return Err(DataError::InvalidScanLimit);
+        }
+
+        self.write_data(addr, Command::ScanLimit, digits - 1)?;
+        Ok(self)
+    }
+
+    ///
+    /// Returns the settings of all displays as last set, which the chips lose
+    /// on a power cycle. Pass them to `restore` to apply them again.
+    ///
+    pub fn snapshot(&self) -> DisplayState<N> {
+        DisplayState {
+            intensities: self.intensities,
+            decode_masks: self.decode_masks,
+            scan_limits: self.scan_limits,
+            powered: self.powered,
+        }
+    }
+
+    ///
+    /// Sends given settings to all connected displays, e.g. after a brown-out
+    /// reset the chips. Everything is sent even if the driver already has the
+    /// same settings. The digits are not restored, write them again afterwards.
+    /// The settings of all displays are checked first, nothing is sent if any is invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - the settings to apply, as returned by `snapshot`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidScanLimit` - returned if a scan limit is not within `1` to `8`
+    /// * `DataError::InvalidIntensity` - returned if an intensity is above `0x0F`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn restore(&mut self, state: &DisplayState<N>) -> Result<(), DataError> {
+        let limits = state.scan_limits[..self.devices].iter();
+        for (digits, intensity) in limits.zip(state.intensities.iter()) {
+            if *digits == 0 || *digits as usize > MAX_DIGITS {
+                return Err(DataError::InvalidScanLimit);
+            }
+            check_intensity(*intensity)?;
+        }
+
+        for addr in 0..self.devices {
+            self.set_scan_limit(addr, state.scan_limits[addr])?;
+            self.write_data(addr, Command::DecodeMode, state.decode_masks[addr])?;
+            self.set_intensity(addr, state.intensities[addr])?;
+            if state.powered[addr] {
+                self.power_on_device(addr)?;
+            } else {
+                self.power_off_device(addr)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Sets decode mode to be used on input sent to the display chip.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `mode` - the decode mode to set
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_decode_mode(
+        &mut self,
+        addr: usize,
+        mode: DecodeMode,
+    ) -> Result<&mut Self, DataError> {
+        self.set_decode_mask(addr, mode as u8)
+    }
+
+    ///
+    /// Sets which digits are Code B decoded, allowing to mix decoded digits
+    /// with raw segments such as a colon. Each bit enables Code B for the
+    /// corresponding digit, bit 0 being `Digit0`. Nothing is sent if the
+    /// display already uses given mask, see `current_decode_mask`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `mask` - the digits to decode (1 = Code B, 0 = raw segments)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_decode_mask(&mut self, addr: usize, mask: u8) -> Result<&mut Self, DataError> {
+        self.check_addr(addr)?;
+        if self.decode_masks[addr] == mask {
+            return Ok(self);
+        }
+
+        self.write_data(addr, Command::DecodeMode, mask)?;
+        Ok(self)
+    }
+
+    ///
+    /// Sets decode mode on all connected displays at once. Unlike `set_decode_mode`
+    /// the mode is always sent, bringing displays back in line with the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - the decode mode to set
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_decode_mode_all(&mut self, mode: DecodeMode) -> Result<&mut Self, DataError> {
+        self.write_data_all(Command::DecodeMode, mode as u8)?;
+        Ok(self)
+    }
+
+    ///
+    /// Returns the decode mode last set on the display, `None` if
+    /// it was given a mask via `set_decode_mask` which is not a `DecodeMode`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    pub fn current_decode_mode(&self, addr: usize) -> Result<Option<DecodeMode>, DataError> {
+        Ok(DecodeMode::from_u8(self.current_decode_mask(addr)?))
+    }
+
+    ///
+    /// Returns the decode mask last set on the display, see `set_decode_mask`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    pub fn current_decode_mask(&self, addr: usize) -> Result<u8, DataError> {
+        self.check_addr(addr)?;
+
+        Ok(self.decode_masks[addr])
+    }
+
+    ///
+    /// Writes byte string to the display
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `string` - the byte string to send 8 bytes long. Unknown characters result in question mark.
+    /// * `dots` - u8 bit array specifying where to put dots in the string (1 = dot, 0 = not)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_str(
+        &mut self,
+        addr: usize,
+        string: &[u8; MAX_DIGITS],
+        dots: u8,
+    ) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::NoDecode)?;
+
+        for [digit, data] in frame::str_pairs(string, dots).iter() {
+            self.write_register(addr, *digit, *data)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes BCD encoded string to the display
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `bcd` - the bcd encoded string slice consisting of [0-9,-,E,L,H,P]
+    ///   where upper case input for alphabetic characters results in dot being set.
+    ///   Length of string is always 8 bytes, use spaces for blanking. Unsupported
+    ///   bytes are shown blank.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_bcd(&mut self, addr: usize, bcd: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::CodeBDigits7_0)?;
+
+        for [digit, data] in frame::bcd_pairs(bcd).iter() {
+            self.write_register(addr, *digit, *data)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes BCD encoded string to the display like `write_bcd`, except that
+    /// bytes without a Code B representation are rejected instead of being
+    /// shown blank
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `bcd` - the bcd encoded string slice, see `write_bcd`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidCharacter` - returned with the first unsupported byte,
+    ///   nothing is sent in that case
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn try_write_bcd(&mut self, addr: usize, bcd: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
+        if let Some(b) = bcd.iter().find(|b| bcd_byte_checked(**b).is_none()) {
+            return Err(DataError::InvalidCharacter(*b));
+        }
+
+        self.write_bcd(addr, bcd)
+    }
+
+    ///
+    /// Writes a raw value to the display
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `raw` - an array of raw bytes to write. Each bit represents a pixel on the display
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_raw(&mut self, addr: usize, raw: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::NoDecode)?;
+
+        for [digit, data] in frame::raw_pairs(raw).iter() {
+            self.write_register(addr, *digit, *data)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes values from an iterator to successive digit registers starting with
+    /// `Digit0`, keeping the current decode mode. At most 8 values are taken,
+    /// digits left over when the iterator ends early are blanked.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `values` - the register values, Code B or raw segments as decoded by the display
     ///
     /// # Errors
     ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn power_on(&mut self) -> Result<(), DataError> {
-        for i in 0..self.c.devices() {
-            self.c.write_data(i, Command::Power, 0x01)?;
+    pub fn write_digits<I>(&mut self, addr: usize, values: I) -> Result<(), DataError>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        self.check_addr(addr)?;
+
+        let mut values = values.into_iter();
+        for digit in Digit::all() {
+            let value = values
+                .next()
+                .unwrap_or_else(|| self.blank_value(addr, digit));
+            self.write_register(addr, digit.register(), value)?;
         }
 
         Ok(())
     }
 
     ///
-    /// Powers off all connected displays
+    /// Set test mode on/off
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `is_on` - whether to turn test mode on or off
     ///
     /// # Errors
     ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn power_off(&mut self) -> Result<(), DataError> {
-        for i in 0..self.c.devices() {
-            self.c.write_data(i, Command::Power, 0x00)?;
+    pub fn test(&mut self, addr: usize, is_on: bool) -> Result<(), DataError> {
+        if is_on {
+            self.write_data(addr, Command::DisplayTest, 0x01)
+        } else {
+            self.write_data(addr, Command::DisplayTest, 0x00)
         }
-
-        Ok(())
     }
 
     ///
-    /// Clears display by settings all digits to empty
+    /// Set test mode on/off on all connected displays at once,
+    /// lighting every LED of the chain while on
     ///
     /// # Arguments
     ///
-    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `is_on` - whether to turn test mode on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn test_all(&mut self, is_on: bool) -> Result<(), DataError> {
+        if is_on {
+            self.write_data_all(Command::DisplayTest, 0x01)
+        } else {
+            self.write_data_all(Command::DisplayTest, 0x00)
+        }
+    }
+
+    ///
+    /// Re-runs the initialization sequence on all connected displays, turning
+    /// test mode off, applying the scan limit, decode mode and intensity the driver
+    /// was built with, clearing and powering them off (or on, see `MAX7219Builder`).
+    /// Useful to recover from glitches without recreating the driver.
     ///
     /// # Errors
     ///
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn clear_display(&mut self, addr: usize) -> Result<(), DataError> {
-        for i in 1..9 {
-            self.c.write_raw(addr, i, 0x00)?;
+    pub fn reset(&mut self) -> Result<(), DataError> {
+        self.init()
+    }
+
+    ///
+    /// Powers off all connected displays and destroys the driver, returning the
+    /// connector so the pins or SPI can be reused. Powering off is best-effort,
+    /// transfer errors are ignored so the connector is always returned.
+    ///
+    pub fn shutdown(mut self) -> CONNECTOR {
+        let _ = self.power_off(); // best-effort, the connector is returned either way
+        self.c
+    }
+
+    ///
+    /// Destroys the driver returning the connector, leaving the displays as they are.
+    /// Call `release` on the connector to get the pins or SPI back.
+    ///
+    pub fn release(self) -> CONNECTOR {
+        self.c
+    }
+
+    ///
+    /// Turns test mode on and off on each display one by one, making sure the
+    /// commands can be sent to every declared display. The chip can not be read
+    /// back, so a chain shorter than declared or a display not responding
+    /// can not be detected, tell by watching all displays light up.
+    ///
+    /// # Errors
+    ///
+    /// * `DeviceError` - returned with the display being written in case there was
+    ///   an error during data transfer
+    ///
+    pub fn verify(&mut self) -> Result<(), DeviceError> {
+        for addr in 0..self.devices {
+            self.test(addr, true)
+                .and_then(|_| self.test(addr, false))
+                .map_err(|error| DeviceError {
+                    addr,
+                    command: Command::DisplayTest,
+                    error,
+                })?;
         }
 
         Ok(())
     }
 
     ///
-    /// Sets intensity level on the display
+    /// Returns the number of displays connected in series
+    ///
+    pub fn device_count(&self) -> usize {
+        self.devices
+    }
+
+    ///
+    /// Returns the connector used to talk to the displays
+    ///
+    pub fn connector(&self) -> &CONNECTOR {
+        &self.c
+    }
+
+    ///
+    /// Returns the connector used to talk to the displays mutably,
+    /// bypassing the driver state when writing to it
+    ///
+    pub fn connector_mut(&mut self) -> &mut CONNECTOR {
+        &mut self.c
+    }
+
+    ///
+    /// Returns the value last written to given digit register. The chip
+    /// can not be read back, so this is the driver's own copy of it.
     ///
     /// # Arguments
     ///
     /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `intensity` - intensity value to set to `0x00` to 0x0F`
+    /// * `digit` - the digit register to read
     ///
     /// # Errors
     ///
-    /// * `DataError` - returned in case there was an error during data transfer
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
     ///
-    pub fn set_intensity(&mut self, addr: usize, intensity: u8) -> Result<(), DataError> {
-        self.c.write_data(addr, Command::Intensity, intensity)
+    pub fn get_digit(&self, addr: usize, digit: Digit) -> Result<u8, DataError> {
+        self.check_addr(addr)?;
+
+        Ok(self.digits[addr][digit.index() as usize])
     }
 
     ///
-    /// Sets decode mode to be used on input sent to the display chip.
+    /// Writes data to given register as described by command
     ///
     /// # Arguments
     ///
     /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `mode` - the decode mode to set
+    /// * `command` - the command/register on the display to write to
+    /// * `data` - the data byte value to write
     ///
     /// # Errors
     ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn set_decode_mode(&mut self, addr: usize, mode: DecodeMode) -> Result<(), DataError> {
-        self.decode_mode = mode; // store what we set
-        self.c.write_data(addr, Command::DecodeMode, mode as u8)
+    pub fn write_data(&mut self, addr: usize, command: Command, data: u8) -> Result<(), DataError> {
+        self.write_register(addr, command as u8, data)
     }
 
     ///
-    /// Writes byte string to the display
+    /// Writes data to given register as described by command on all
+    /// connected displays at once
     ///
     /// # Arguments
     ///
-    /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `string` - the byte string to send 8 bytes long. Unknown characters result in question mark.
-    /// * `dots` - u8 bit array specifying where to put dots in the string (1 = dot, 0 = not)
+    /// * `command` - the command/register on the displays to write to
+    /// * `data` - the data byte value to write
     ///
     /// # Errors
     ///
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn write_str(
-        &mut self,
-        addr: usize,
-        string: &[u8; MAX_DIGITS],
-        dots: u8,
-    ) -> Result<(), DataError> {
-        let prev_dm = self.decode_mode;
-        self.set_decode_mode(0, DecodeMode::NoDecode)?;
-
-        let mut digit: u8 = MAX_DIGITS as u8;
-        let mut dot_product: u8 = 0b1000_0000;
-        for b in string {
-            let dot = (dots & dot_product) > 0;
-            dot_product >>= 1;
-            self.c.write_raw(addr, digit, ssb_byte(*b, dot))?;
-
-            digit -= 1;
-        }
-
-        self.set_decode_mode(0, prev_dm)?;
-
-        Ok(())
+    pub fn write_data_all(&mut self, command: Command, data: u8) -> Result<(), DataError> {
+        self.write_register_all(command as u8, data)
     }
 
     ///
-    /// Writes BCD encoded string to the display
+    /// Writes a raw header and data byte pair to all connected displays at once,
+    /// for registers not covered by `Command`
     ///
     /// # Arguments
     ///
-    /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `bcd` - the bcd encoded string slice consisting of [0-9,-,E,L,H,P]
-    /// where upper case input for alphabetic characters results in dot being set.
-    /// Length of string is always 8 bytes, use spaces for blanking.
+    /// * `header` - the register address byte to write to
+    /// * `data` - the data byte value to write
     ///
     /// # Errors
     ///
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn write_bcd(&mut self, addr: usize, bcd: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
-        let prev_dm = self.decode_mode;
-        self.set_decode_mode(0, DecodeMode::CodeBDigits7_0)?;
+    pub fn write_raw_all(&mut self, header: u8, data: u8) -> Result<(), DataError> {
+        self.write_register_all(header, data)
+    }
 
-        let mut digit: u8 = MAX_DIGITS as u8;
-        for b in bcd {
-            self.c.write_raw(addr, digit, bcd_byte(*b))?;
+    ///
+    /// Writes a list of registers to all connected displays in order, e.g. to
+    /// apply a whole configuration at once. Each entry is a single broadcast
+    /// frame, repeated entries following each other are only sent once.
+    ///
+    /// # Arguments
+    ///
+    /// * `commands` - command/register and raw data byte pairs to write
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_command_sequence(&mut self, commands: &[(Command, u8)]) -> Result<(), DataError> {
+        let mut last = None;
+        for &(command, data) in commands {
+            if last == Some((command, data)) {
+                continue;
+            }
 
-            digit -= 1;
+            self.write_data_all(command, data)?;
+            last = Some((command, data));
         }
 
-        self.set_decode_mode(0, prev_dm)?;
-
         Ok(())
     }
 
     ///
-    /// Writes a raw value to the display
+    /// Writes a pre-built frame of the whole chain in a single CS cycle,
+    /// allowing to send different commands to each display at once
     ///
     /// # Arguments
     ///
-    /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `raw` - an array of raw bytes to write. Each bit represents a pixel on the display
+    /// * `frame` - header and data byte pairs, one pair per display
+    ///   as connected in series (first pair -> last display)
     ///
     /// # Errors
     ///
+    /// * `DataError::InvalidLength` - returned if `frame` is not 2 bytes per display
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn write_raw(&mut self, addr: usize, raw: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
-        let prev_dm = self.decode_mode;
-        self.set_decode_mode(0, DecodeMode::NoDecode)?;
-
-        let mut digit: u8 = 1;
-        for b in raw {
-            self.c.write_raw(addr, digit, *b)?;
-            digit += 1;
+    pub fn write_chain(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        if frame.len() != self.devices * 2 {
+            return Err(DataError::InvalidLength);
         }
 
-        self.set_decode_mode(0, prev_dm)?;
+        for (pair, bytes) in self.buffer.iter_mut().zip(frame.chunks(2)) {
+            *pair = [bytes[0], bytes[1]];
+        }
 
-        Ok(())
+        self.write_buffer()
     }
 
     ///
-    /// Set test mode on/off
+    /// Construct a new MAX7219 driver instance from a connector, supporting
+    /// up to `N` displays connected in series. Use this to drive chains
+    /// longer than `MAX_DISPLAYS` or to provide a custom `Connector`.
+    /// All 8 digits are scanned, use `MAX7219Builder::scan_limit` for modules
+    /// with fewer digits.
     ///
     /// # Arguments
     ///
-    /// * `addr` - display to address as connected in series (0 -> last)
-    /// * `is_on` - whether to turn test mode on or off
+    /// * `displays` - number of displays connected in series, 1 to `N`
+    /// * `connector` - the interface used to send data to the displays
     ///
     /// # Errors
     ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `N`
     /// * `DataError` - returned in case there was an error during data transfer
     ///
-    pub fn test(&mut self, addr: usize, is_on: bool) -> Result<(), DataError> {
-        if is_on {
-            self.c.write_data(addr, Command::DisplayTest, 0x01)
-        } else {
-            self.c.write_data(addr, Command::DisplayTest, 0x00)
+    pub fn from_connector(displays: usize, connector: CONNECTOR) -> Result<Self, DataError> {
+        MAX7219Builder::new(displays).build(connector)
+    }
+
+    ///
+    /// Construct a new MAX7219 driver instance from a connector like `from_connector`,
+    /// but initializing and verifying the displays one by one to report which display
+    /// and command failed, see `MAX7219Builder::build_verified`.
+    ///
+    /// # Arguments
+    ///
+    /// * `displays` - number of displays connected in series, 1 to `N`
+    /// * `connector` - the interface used to send data to the displays
+    ///
+    /// # Errors
+    ///
+    /// * `DeviceError` - returned for the same reasons as `MAX7219Builder::build_verified`
+    ///
+    pub fn from_connector_verified(
+        displays: usize,
+        connector: CONNECTOR,
+    ) -> Result<Self, DeviceError> {
+        MAX7219Builder::new(displays).build_verified(connector)
+    }
+
+    // writes to a single register on given display, sending no-ops to the rest.
+    // Consecutive calls store 4 buffer bytes each instead of the 2 * N + 2 of clearing
+    // the whole buffer (18 for the default N = 8), the transfer stays one
+    // frame of 2 bytes per connected display either way.
+    fn write_register(&mut self, addr: usize, header: u8, data: u8) -> Result<(), DataError> {
+        self.check_addr(addr)?;
+
+        // after a single register write only that pair needs clearing
+        frame::fill_register(&mut self.buffer, self.single, addr, header, data);
+
+        self.write_buffer()?;
+        self.single = Some(addr);
+        Ok(())
+    }
+
+    // writes the same register and value to all connected displays
+    fn write_register_all(&mut self, header: u8, data: u8) -> Result<(), DataError> {
+        self.buffer = [[header, data]; N];
+
+        self.write_buffer()
+    }
+
+    // fails for addresses outside of the connected chain
+    fn check_addr(&self, addr: usize) -> Result<(), DataError> {
+        frame::check_addr(addr, self.devices)
+    }
+
+    // whether the digits of each display are complemented on the wire, which is the
+    // case for either a negative matrix image or inverted segment logic but not both
+    fn inversions(&self) -> [bool; N] {
+        #[allow(unused_mut)] // only changed by segment polarities
+        let mut inversions = self.inverted;
+        #[cfg(feature = "segment")]
+        for (inverted, polarity) in inversions.iter_mut().zip(self.polarities.iter()) {
+            *inverted ^= *polarity == SegmentPolarity::Inverted;
         }
+
+        inversions
     }
 
-    // internal constructor, users should call ::from_pins or ::from_spi
-    fn new(connector: CONNECTOR) -> Result<Self, DataError> {
-        let mut max7219 = MAX7219 {
-            c: connector,
-            decode_mode: DecodeMode::NoDecode,
-        };
+    // shifts the buffer out to all connected displays, remembering digit values
+    // and inverting them on the wire for inverted displays. Settings sent are
+    // remembered as well to be reported by `snapshot`.
+    fn write_buffer(&mut self) -> Result<(), DataError> {
+        self.single = None;
 
-        max7219.init()?;
-        Ok(max7219)
+        let inversions = self.inversions();
+        let displays = self.digits.iter_mut().zip(self.sent.iter_mut());
+        let displays = displays.zip(inversions.iter());
+        for (((digits, sent), inverted), [header, data]) in displays.zip(self.buffer.iter_mut()) {
+            if let Some(digit) = digit_index(*header) {
+                digits[digit] = *data;
+                if *inverted {
+                    *data = !*data;
+                }
+                sent[digit] = *data;
+            }
+        }
+
+        #[cfg(feature = "defmt")]
+        for (addr, [header, data]) in self.buffer[..self.devices].iter().enumerate() {
+            if *header != Command::Noop as u8 {
+                defmt::trace!(
+                    "addr {} header {=u8:#04x} data {=u8:#04x}",
+                    addr,
+                    header,
+                    data
+                );
+            }
+        }
+
+        self.c
+            .write_frame(self.buffer[..self.devices].as_flattened())?;
+
+        for (addr, [header, data]) in self.buffer.iter().enumerate() {
+            match Setting::sent(*header, *data) {
+                Some(Setting::DecodeMode(mask)) => self.decode_masks[addr] = mask,
+                Some(Setting::Intensity(intensity)) => self.intensities[addr] = intensity,
+                Some(Setting::ScanLimit(digits)) => self.scan_limits[addr] = digits,
+                Some(Setting::Power(on)) => self.powered[addr] = on,
+                None => {}
+            }
+        }
+
+        Ok(())
     }
 
     fn init(&mut self) -> Result<(), DataError> {
-        for i in 0..self.c.devices() {
-            self.test(i, false)?; // turn testmode off
-            self.c.write_data(i, Command::ScanLimit, 0x07)?; // set scanlimit
-            self.set_decode_mode(i, DecodeMode::NoDecode)?; // direct decode
-            self.clear_display(i)?; // clear all digits
+        let settings = self.settings;
+
+        for (command, data) in frame::init_commands(&settings) {
+            self.write_data_all(command, data)?;
+        }
+        self.clear_all()?; // clear all digits
+        let (command, data) = frame::power_command(&settings);
+        self.write_data_all(command, data)
+    }
+
+    // runs the initialization sequence on each display one by one
+    fn init_each(&mut self) -> Result<(), DeviceError> {
+        let settings = self.settings;
+        self.decode_masks = [settings.decode_mode as u8; N];
+
+        let mut blanks = [0; MAX_DIGITS];
+        for (digit, blank) in Digit::all().zip(blanks.iter_mut()) {
+            *blank = self.blank_value(0, digit);
+        }
+
+        for addr in 0..self.devices {
+            let mut write = |command: Command, data: u8| {
+                self.write_data(addr, command, data)
+                    .map_err(|error| DeviceError {
+                        addr,
+                        command,
+                        error,
+                    })
+            };
+
+            for (command, data) in frame::init_commands(&settings) {
+                write(command, data)?;
+            }
+            for (digit, blank) in Digit::all().zip(blanks.iter()) {
+                write(digit.command(), *blank)?;
+            }
+            let (command, data) = frame::power_command(&settings);
+            write(command, data)?;
         }
-        self.power_off()?; // power off
 
         Ok(())
     }
+
+    // value showing given digit register as empty in current decode mode of the display
+    fn blank_value(&self, addr: usize, digit: Digit) -> u8 {
+        frame::blank_value(self.decode_masks[addr], digit)
+    }
 }
 
 impl<DATA, CS, SCK> MAX7219<PinConnector<DATA, CS, SCK>>
@@ -309,17 +1562,57 @@ where
     ///
     /// # Arguments
     ///
-    /// * `displays` - number of displays connected in series
+    /// * `displays` - number of displays connected in series, 1 to `MAX_DISPLAYS`
     /// * `data` - the MOSI/DATA PIN used to send data through to the display set to output mode
     /// * `cs` - the CS PIN used to LOAD register on the display set to output mode
     /// * `sck` - the SCK clock PIN used to drive the clock set to output mode
     ///
     /// # Errors
     ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
     /// * `DataError` - returned in case there was an error during data transfer
     ///
     pub fn from_pins(displays: usize, data: DATA, cs: CS, sck: SCK) -> Result<Self, DataError> {
-        MAX7219::new(PinConnector::new(displays, data, cs, sck))
+        MAX7219::from_connector(displays, PinConnector::new(data, cs, sck))
+    }
+}
+
+impl<DATA, CS, SCK, DELAY> MAX7219<PinConnector<DATA, CS, SCK, DELAY>>
+where
+    DATA: OutputPin,
+    CS: OutputPin,
+    SCK: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    ///
+    /// Construct a new MAX7219 driver instance from DATA, CS and SCK pins,
+    /// waiting `pulse_us` after each clock edge for slow level shifters or long wires.
+    ///
+    /// # Arguments
+    ///
+    /// * `displays` - number of displays connected in series, 1 to `MAX_DISPLAYS`
+    /// * `data` - the MOSI/DATA PIN used to send data through to the display set to output mode
+    /// * `cs` - the CS PIN used to LOAD register on the display set to output mode
+    /// * `sck` - the SCK clock PIN used to drive the clock set to output mode
+    /// * `delay` - the delay used between clock edges
+    /// * `pulse_us` - microseconds to wait after each clock edge
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn from_pins_with_delay(
+        displays: usize,
+        data: DATA,
+        cs: CS,
+        sck: SCK,
+        delay: DELAY,
+        pulse_us: u32,
+    ) -> Result<Self, DataError> {
+        let connector = PinConnector::new_with_delay(data, cs, sck, delay, pulse_us);
+
+        MAX7219::from_connector(displays, connector)
     }
 }
 
@@ -336,15 +1629,16 @@ where
     ///
     /// # Arguments
     ///
-    /// * `displays` - number of displays connected in series
+    /// * `displays` - number of displays connected in series, 1 to `MAX_DISPLAYS`
     /// * `spi` - the SPI interface initialized with MOSI, MISO(unused) and CLK
     ///
     /// # Errors
     ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
     /// * `DataError` - returned in case there was an error during data transfer
     ///
     pub fn from_spi(displays: usize, spi: SPI) -> Result<Self, DataError> {
-        MAX7219::new(SpiConnector::new(displays, spi))
+        MAX7219::from_connector(displays, SpiConnector::new(spi))
     }
 }
 
@@ -362,25 +1656,59 @@ where
     ///
     /// # Arguments
     ///
-    /// * `displays` - number of displays connected in series
+    /// * `displays` - number of displays connected in series, 1 to `MAX_DISPLAYS`
     /// * `spi` - the SPI interface initialized with MOSI, MISO(unused) and CLK
     /// * `cs` - the CS PIN used to LOAD register on the display set to output mode
     ///
     /// # Errors
     ///
+    /// * `DataError::InvalidDisplayCount` - returned if `displays` is 0 or exceeds `MAX_DISPLAYS`
     /// * `DataError` - returned in case there was an error during data transfer
     ///
     pub fn from_spi_cs(displays: usize, spi: SPI, cs: CS) -> Result<Self, DataError> {
-        MAX7219::new(SpiConnectorSW::new(displays, spi, cs))
+        MAX7219::from_connector(displays, SpiConnectorSW::new(spi, cs))
+    }
+}
+
+///
+/// Fails for intensity values above the 16 steps of the chip.
+///
+fn check_intensity(intensity: u8) -> Result<(), DataError> {
+    if intensity > MAX_INTENSITY {
+        return Err(DataError::InvalidIntensity(intensity));
+    }
+
+    Ok(())
+}
+
+///
+/// Translate a digit register header into an index
+/// of the digit, `None` for control registers.
+///
+fn digit_index(header: u8) -> Option<usize> {
+    match header {
+        0x01..=0x08 => Some(header as usize - 1),
+        _ => None,
     }
 }
 
 ///
 /// Translate alphanumeric ASCII bytes into BCD
 /// encoded bytes expected by the display chip.
+/// Code B only supports `0-9`, `-`, `E`, `H`, `L`, `P` and blank,
+/// any other byte falls back to blank.
 ///
 fn bcd_byte(b: u8) -> u8 {
-    match b as char {
+    bcd_byte_checked(b).unwrap_or(0b0000_1111)
+}
+
+///
+/// Translate alphanumeric ASCII bytes into BCD encoded bytes,
+/// `None` for bytes which Code B does not support.
+///
+fn bcd_byte_checked(b: u8) -> Option<u8> {
+    let value = match b as char {
+        '0'..='9' => b - b'0',
         ' ' => 0b0000_1111, // "blank"
         '-' => 0b0000_1010, // - without .
         'e' => 0b0000_1011, // E without .
@@ -391,15 +1719,34 @@ fn bcd_byte(b: u8) -> u8 {
         'L' => 0b1000_1101, // L with .
         'p' => 0b0000_1110, // L without .
         'P' => 0b1000_1110, // L with .
-        _ => b,
-    }
+        _ => return None,
+    };
+
+    Some(value)
 }
 
 ///
-/// Translate alphanumeric ASCII bytes into segment set bytes
+/// Translate alphanumeric ASCII bytes into segment set bytes.
+/// Supports ` `, `.`, `-`, `_`, `0-9` and the letters
+/// `A b C d E F G H I J L n O P q r S t U y` in either case, except
+/// `b d n q r` which are lower case only. Anything else shows a question mark.
 ///
 fn ssb_byte(b: u8, dot: bool) -> u8 {
-    let mut result = match b as char {
+    let mut result = ssb_byte_checked(b).unwrap_or(0b1110_0101); // ?
+
+    if dot {
+        result |= 0b1000_0000; // turn "." on
+    }
+
+    result
+}
+
+///
+/// Translate alphanumeric ASCII bytes into segment set bytes without a dot,
+/// `None` for bytes which have no segment pattern.
+///
+fn ssb_byte_checked(b: u8) -> Option<u8> {
+    let segments = match b as char {
         ' ' => 0b0000_0000, // "blank"
         '.' => 0b1000_0000,
         '-' => 0b0000_0001, // -
@@ -427,25 +1774,307 @@ fn ssb_byte(b: u8, dot: bool) -> u8 {
         // K undoable
         'l' | 'L' => 0b0000_1110,
         // M undoable
+        'n' => 0b0001_0101,
         // N undoable
         'o' | 'O' => 0b0111_1110,
         'p' | 'P' => 0b0110_0111,
         'q' => 0b0111_0011,
+        'r' => 0b0000_0101,
         // R undoable
         's' | 'S' => 0b0101_1011,
-        // T undoable
+        't' | 'T' => 0b0000_1111,
         'u' | 'U' => 0b0011_1110,
         // V undoable
         // W undoable
         // X undoable
-        // Y undoable
+        'y' | 'Y' => 0b0011_1011,
         // Z undoable
-        _ => 0b1110_0101, // ?
+        '?' => 0b1110_0101,
+        _ => return None,
     };
 
-    if dot {
-        result |= 0b1000_0000; // turn "." on
+    Some(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::RecordingConnector;
+    use crate::test_util::{display, Display};
+
+    #[test]
+    fn rejects_invalid_display_count() {
+        let zero: Result<Display, _> = MAX7219::from_connector(0, RecordingConnector::new());
+        assert_eq!(zero.err(), Some(DataError::InvalidDisplayCount));
+
+        let nine: Result<Display, _> = MAX7219::from_connector(9, RecordingConnector::new());
+        assert_eq!(nine.err(), Some(DataError::InvalidDisplayCount));
+
+        assert_eq!(display(MAX_DISPLAYS).device_count(), MAX_DISPLAYS);
     }
 
-    result
+    #[test]
+    fn maps_code_b_characters() {
+        for (i, b) in (b'0'..=b'9').enumerate() {
+            assert_eq!(bcd_byte(b), i as u8);
+        }
+        let mapped = [
+            (b' ', 0x0F),
+            (b'-', 0x0A),
+            (b'e', 0x0B),
+            (b'E', 0x8B),
+            (b'h', 0x0C),
+            (b'H', 0x8C),
+            (b'l', 0x0D),
+            (b'L', 0x8D),
+            (b'p', 0x0E),
+            (b'P', 0x8E),
+        ];
+        for (b, value) in mapped.iter() {
+            assert_eq!(bcd_byte(*b), *value);
+        }
+
+        // unsupported characters are blank
+        for b in b"AbxZ?\x00\x0B".iter() {
+            assert_eq!(bcd_byte_checked(*b), None);
+            assert_eq!(bcd_byte(*b), 0x0F);
+        }
+    }
+
+    #[test]
+    fn maps_segment_characters() {
+        let digits = [0x7E, 0x30, 0x6D, 0x79, 0x33, 0x5B, 0x5F, 0x70, 0x7F, 0x7B];
+        for (b, segments) in (b'0'..=b'9').zip(digits.iter()) {
+            assert_eq!(ssb_byte(b, false), *segments);
+            assert_eq!(ssb_byte(b, true), *segments | 0x80);
+        }
+        let letters = [
+            (b'A', 0x77),
+            (b'b', 0x1F),
+            (b'C', 0x4E),
+            (b'd', 0x3D),
+            (b'E', 0x4F),
+            (b'F', 0x47),
+            (b'G', 0x5E),
+            (b'H', 0x37),
+            (b'I', 0x30),
+            (b'J', 0x3C),
+            (b'L', 0x0E),
+            (b'n', 0x15),
+            (b'O', 0x7E),
+            (b'P', 0x67),
+            (b'q', 0x73),
+            (b'r', 0x05),
+            (b'S', 0x5B),
+            (b't', 0x0F),
+            (b'U', 0x3E),
+            (b'y', 0x3B),
+            (b' ', 0x00),
+            (b'-', 0x01),
+            (b'_', 0x08),
+        ];
+        for (b, segments) in letters.iter() {
+            assert_eq!(ssb_byte(*b, false), *segments);
+        }
+
+        // upper and lower case share a pattern where both are supported
+        assert_eq!(ssb_byte(b'a', false), ssb_byte(b'A', false));
+        assert_eq!(ssb_byte(b'K', false), 0b1110_0101);
+        assert_eq!(ssb_byte_checked(b'K'), None);
+        assert_eq!(ssb_byte_checked(b'?'), Some(0b1110_0101));
+    }
+
+    #[test]
+    fn write_bcd_sets_decode_mode_on_addressed_display() {
+        let mut display = display(3);
+        display.set_decode_mode_all(DecodeMode::NoDecode).unwrap();
+        display.connector_mut().clear();
+
+        display.write_bcd(2, b"12345678").unwrap();
+
+        // the first pair of a frame goes to display 0
+        let frame = display.connector().frame(0).unwrap();
+        assert_eq!(frame, &[0x00, 0x00, 0x00, 0x00, 0x09, 0xFF]);
+        assert!(display.connector().commands().all(|(addr, _, _)| addr == 2));
+        assert_eq!(display.current_decode_mask(2), Ok(0xFF));
+        assert_eq!(display.current_decode_mask(0), Ok(0x00));
+    }
+
+    #[test]
+    fn write_bcd_fills_all_digit_registers() {
+        let mut display = display(1);
+
+        display.write_bcd(0, b"12345678").unwrap();
+        let digits = display
+            .connector()
+            .commands()
+            .filter(|(_, register, _)| *register <= 8);
+        // the leftmost character goes to Digit7
+        assert!(digits.eq([
+            (0, 8, 1),
+            (0, 7, 2),
+            (0, 6, 3),
+            (0, 5, 4),
+            (0, 4, 5),
+            (0, 3, 6),
+            (0, 2, 7),
+            (0, 1, 8)
+        ]
+        .iter()
+        .copied()));
+
+        display.write_bcd(0, b"123     ").unwrap();
+        let values = Digit::all().map(|digit| display.get_digit(0, digit).unwrap());
+        assert!(values.eq([0x0F, 0x0F, 0x0F, 0x0F, 0x0F, 3, 2, 1].iter().copied()));
+    }
+
+    #[test]
+    fn rejects_address_past_chain() {
+        let mut display = display(2);
+        let out = Some(DataError::AddressOutOfRange(2));
+
+        assert_eq!(display.write_data(2, Command::Intensity, 0x01).err(), out);
+        assert_eq!(display.set_intensity(2, 0x01).err(), out);
+        assert_eq!(display.clear_display(2).err(), out);
+        assert_eq!(display.power_on_device(2).err(), out);
+        assert_eq!(display.set_scan_limit(2, 8).err(), out);
+        assert_eq!(display.write_bcd(2, b"        ").err(), out);
+        assert_eq!(display.write_raw(2, &[0; MAX_DIGITS]).err(), out);
+        assert_eq!(display.get_digit(2, Digit::new(0).unwrap()).err(), out);
+        assert_eq!(display.connector().frame_count(), 0);
+
+        // the last display in the chain is still addressable
+        assert!(display.write_data(1, Command::Intensity, 0x01).is_ok());
+    }
+
+    #[test]
+    fn validates_intensity() {
+        let mut display = display(1);
+
+        assert_eq!(
+            display.set_intensity(0, 0x20).err(),
+            Some(DataError::InvalidIntensity(0x20))
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+
+        assert!(display.set_intensity(0, 0x0F).is_ok());
+        assert!(display
+            .connector()
+            .commands()
+            .eq([(0, 0x0A, 0x0F)].iter().copied()));
+    }
+
+    #[test]
+    fn writes_each_command_in_one_frame() {
+        let mut display = display(3);
+
+        display.write_data(1, Command::Intensity, 0x03).unwrap();
+        assert_eq!(display.connector().frame_count(), 1);
+        assert_eq!(
+            display.connector().frame(0),
+            Some(&[0, 0, 0x0A, 0x03, 0, 0][..])
+        );
+
+        display.connector_mut().clear();
+        display.write_data_all(Command::Intensity, 0x05).unwrap();
+        assert_eq!(display.connector().frame_count(), 1);
+        assert_eq!(
+            display.connector().frame(0),
+            Some(&[0x0A, 0x05, 0x0A, 0x05, 0x0A, 0x05][..])
+        );
+    }
+
+    #[test]
+    fn snapshot_follows_raw_register_writes() {
+        let mut display = display(2);
+
+        display.write_data_all(Command::Intensity, 0x02).unwrap();
+        display.write_data(1, Command::Intensity, 0x07).unwrap();
+        display.write_data_all(Command::ScanLimit, 0x03).unwrap();
+        display.write_data_all(Command::Power, 0x00).unwrap();
+        display.write_chain(&[0x09, 0xF0, 0x0C, 0x01]).unwrap();
+
+        let state = display.snapshot();
+        assert_eq!(state.intensities[..2], [0x02, 0x07]);
+        assert_eq!(state.scan_limits[..2], [4, 4]);
+        assert_eq!(state.decode_masks[..2], [0xF0, 0x00]);
+        assert_eq!(state.powered[..2], [false, true]);
+    }
+
+    #[test]
+    fn restore_checks_every_display_first() {
+        let mut display = display(2);
+        let valid = display.snapshot();
+
+        let mut state = valid;
+        state.intensities[1] = 0x10;
+        assert_eq!(
+            display.restore(&state).err(),
+            Some(DataError::InvalidIntensity(0x10))
+        );
+
+        let mut state = valid;
+        state.scan_limits[1] = 9;
+        assert_eq!(
+            display.restore(&state).err(),
+            Some(DataError::InvalidScanLimit)
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+
+        display.restore(&valid).unwrap();
+        assert_eq!(display.snapshot(), valid);
+    }
+
+    #[test]
+    fn broadcasts_raw_pairs() {
+        let mut display = display(3);
+
+        display.write_raw_all(0x0F, 0x01).unwrap();
+
+        assert_eq!(display.connector().frame_count(), 1);
+        assert_eq!(
+            display.connector().frame(0),
+            Some(&[0x0F, 0x01, 0x0F, 0x01, 0x0F, 0x01][..])
+        );
+    }
+
+    #[test]
+    fn dimmed_state_never_brightens() {
+        let mut display = display(1);
+        display.set_state(0, PowerState::Off).unwrap();
+        display.set_intensity(0, 0x02).unwrap();
+
+        display.set_state(0, PowerState::Dimmed(0x08)).unwrap();
+        assert_eq!(display.snapshot().intensities[0], 0x02);
+        assert!(display.snapshot().powered[0]);
+
+        display.set_state(0, PowerState::On(0x08)).unwrap();
+        assert_eq!(display.snapshot().intensities[0], 0x08);
+
+        display.set_state(0, PowerState::Dimmed(0x04)).unwrap();
+        assert_eq!(display.snapshot().intensities[0], 0x04);
+
+        let invalid = display.set_state(0, PowerState::Dimmed(0x10)).err();
+        assert_eq!(invalid, Some(DataError::InvalidIntensity(0x10)));
+    }
+
+    #[test]
+    fn try_write_bcd_sends_nothing_on_invalid_bytes() {
+        let mut display = display(2);
+        display.write_raw(1, &[0x11; MAX_DIGITS]).unwrap();
+        display.connector_mut().clear();
+
+        assert_eq!(
+            display.try_write_bcd(1, b"12A4567?").err(),
+            Some(DataError::InvalidCharacter(b'A'))
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+        assert_eq!(display.current_decode_mask(1), Ok(0x00));
+        assert_eq!(display.get_digit(1, Digit::new(7).unwrap()), Ok(0x11));
+
+        display.try_write_bcd(1, b"-1 2HELP").unwrap();
+        assert_eq!(display.current_decode_mask(1), Ok(0xFF));
+        assert_eq!(display.get_digit(1, Digit::new(7).unwrap()), Ok(0x0A));
+        assert_eq!(display.get_digit(1, Digit::new(0).unwrap()), Ok(0x8E));
+    }
 }
@@ -0,0 +1,960 @@
+//! Pixel level access for 8x8 LED matrix displays
+//!
+//! The MAX7219 drives a matrix through its digit registers, where each
+//! "digit" is one row of pixels. `Digit0` maps to row 0 and each bit of
+//! the register value to a column, the most significant bit being column 0.
+//!
+//! The chip can not be read back so the driver keeps the last value
+//! written to every row, which is used to change single pixels.
+//!
+//! Pixels are either on or off. The chip has a single intensity for all
+//! of its LEDs, so brightness can only be changed per display with
+//! `set_intensity`, there is no way to dim single pixels.
+//!
+//! Matrices mounted rotated or mirrored can be given an `Orientation` and
+//! flips, which `write_matrix`, `write_matrix_all` and `set_pixel` apply
+//! before writing, flips after rotating. `write_row` always writes the physical row.
+//! Sprites can instead be prepared once with `transpose_frame`, `mirror_frame`
+//! and `rotate_frame_90`.
+//!
+//! Between `begin_frame` and `end_frame` matrix writes are buffered in that
+//! row shadow and only the changed rows are sent by `flush`.
+
+use crate::connectors::Connector;
+use crate::{frame, DataError, Digit, MAX7219, MAX_DIGITS};
+
+/// Clockwise rotation of the content shown on a matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+///
+/// Pixel output of LED matrices, allowing code to be generic
+/// over what kind of display it writes to.
+///
+pub trait MatrixWrite {
+    ///
+    /// Turns a single pixel on or off, see `MAX7219::set_pixel`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned if the pixel could not be written
+    ///
+    fn set_pixel(&mut self, addr: usize, x: u8, y: u8, on: bool) -> Result<(), DataError>;
+
+    ///
+    /// Sends pending changes to the displays, see `MAX7219::flush`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned if the changes could not be sent
+    ///
+    fn flush(&mut self) -> Result<(), DataError>;
+}
+
+/// Physical mounting of a matrix
+#[derive(Clone, Copy)]
+pub(crate) struct Layout {
+    pub(crate) orientation: Orientation,
+    pub(crate) flip_h: bool,
+    pub(crate) flip_v: bool,
+}
+
+impl Layout {
+    pub(crate) const NORMAL: Layout = Layout {
+        orientation: Orientation::Normal,
+        flip_h: false,
+        flip_v: false,
+    };
+
+    // whether logical rows are the physical rows
+    #[cfg(feature = "graphics")]
+    pub(crate) fn keeps_rows(&self) -> bool {
+        matches!(self.orientation, Orientation::Normal) && !self.flip_v
+    }
+
+    // translates a logical pixel position into the physical one
+    pub(crate) fn pixel(&self, x: u8, y: u8) -> (u8, u8) {
+        let max = MAX_DIGITS as u8 - 1;
+        let (mut x, mut y) = orient_pixel(self.orientation, x, y);
+        if self.flip_h {
+            x = max - x;
+        }
+        if self.flip_v {
+            y = max - y;
+        }
+
+        (x, y)
+    }
+
+    // translates a logical frame into the physical rows
+    pub(crate) fn frame(&self, frame: &[u8; MAX_DIGITS]) -> [u8; MAX_DIGITS] {
+        let mut result = orient_frame(self.orientation, frame);
+        if self.flip_h {
+            for bits in result.iter_mut() {
+                *bits = bits.reverse_bits();
+            }
+        }
+        if self.flip_v {
+            result.reverse();
+        }
+
+        result
+    }
+}
+
+impl<CONNECTOR, const N: usize> MAX7219<CONNECTOR, N>
+where
+    CONNECTOR: Connector,
+{
+    ///
+    /// Writes one row of pixels to the matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `row` - the row to write to, `0` to `7`
+    /// * `bits` - the row pixels, most significant bit being column 0 (1 = on, 0 = off)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `row` is outside of the matrix
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_row(&mut self, addr: usize, row: u8, bits: u8) -> Result<(), DataError> {
+        if row as usize >= MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+        if self.buffered {
+            self.check_addr(addr)?;
+
+            self.digits[addr][row as usize] = bits;
+            return Ok(());
+        }
+
+        self.write_register(addr, row + 1, bits)
+    }
+
+    ///
+    /// Writes the same physical row of every connected display in a single
+    /// CS cycle, e.g. to draw a line across the whole chain
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - the row to write to, `0` to `7`
+    /// * `bytes` - the row pixels of each display as connected in series (0 -> last),
+    ///   most significant bit being column 0 (1 = on, 0 = off)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `row` is outside of the matrix
+    /// * `DataError::InvalidLength` - returned if there is not exactly one byte per display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_row_all(&mut self, row: u8, bytes: &[u8]) -> Result<(), DataError> {
+        if row as usize >= MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+        if bytes.len() != self.devices {
+            return Err(DataError::InvalidLength);
+        }
+        if self.buffered {
+            for (addr, bits) in bytes.iter().enumerate() {
+                self.digits[addr][row as usize] = *bits;
+            }
+            return Ok(());
+        }
+
+        for (pair, bits) in self.buffer.iter_mut().zip(bytes.iter()) {
+            *pair = [row + 1, *bits];
+        }
+
+        self.write_buffer()
+    }
+
+    ///
+    /// Returns the pixels of given physical row as last written,
+    /// or as buffered since `begin_frame`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `row` - the row to read, `0` to `7`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `row` is outside of the matrix
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    pub fn get_row(&self, addr: usize, row: u8) -> Result<u8, DataError> {
+        match Digit::new(row) {
+            Some(digit) => self.get_digit(addr, digit),
+            None => Err(DataError::InvalidPosition),
+        }
+    }
+
+    ///
+    /// Turns a single pixel of the matrix on or off, keeping the
+    /// rest of the row as it was last written. Lit pixels share the
+    /// intensity of the display, see `set_intensity`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `x` - the column of the pixel, `0` to `7`
+    /// * `y` - the row of the pixel, `0` to `7`
+    /// * `on` - whether to turn the pixel on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `x` or `y` is outside of the matrix
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_pixel(&mut self, addr: usize, x: u8, y: u8, on: bool) -> Result<(), DataError> {
+        self.update_pixel(
+            addr,
+            x,
+            y,
+            |bits, mask| {
+                if on {
+                    bits | mask
+                } else {
+                    bits & !mask
+                }
+            },
+        )
+    }
+
+    ///
+    /// Returns the width in pixels of the chain as one canvas for `set_pixel_xy`
+    /// and the scroller. Orientations rotate each square matrix in place, so the
+    /// width is `8 * devices` for any of them.
+    ///
+    pub fn pixel_width(&self) -> usize {
+        self.devices * MAX_DIGITS
+    }
+
+    ///
+    /// Returns the height in pixels of the chain as one canvas, 8 for any orientation
+    ///
+    pub fn pixel_height(&self) -> usize {
+        MAX_DIGITS
+    }
+
+    ///
+    /// Turns a single pixel on or off addressing the whole chain as one
+    /// `8 * devices` wide canvas, display 0 being the leftmost
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - the column of the pixel, `0` to `8 * devices - 1`
+    /// * `y` - the row of the pixel, `0` to `7`
+    /// * `on` - whether to turn the pixel on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `x` or `y` is outside of the canvas
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_pixel_xy(&mut self, x: usize, y: usize, on: bool) -> Result<(), DataError> {
+        if x >= self.pixel_width() || y >= self.pixel_height() {
+            return Err(DataError::InvalidPosition);
+        }
+
+        self.set_pixel(x / MAX_DIGITS, (x % MAX_DIGITS) as u8, y as u8, on)
+    }
+
+    ///
+    /// Turns a single pixel of the matrix off, keeping the
+    /// rest of the row as it was last written
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `x` - the column of the pixel, `0` to `7`
+    /// * `y` - the row of the pixel, `0` to `7`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `x` or `y` is outside of the matrix
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn clear_pixel(&mut self, addr: usize, x: u8, y: u8) -> Result<(), DataError> {
+        self.set_pixel(addr, x, y, false)
+    }
+
+    ///
+    /// Flips a single pixel of the matrix, keeping the
+    /// rest of the row as it was last written
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `x` - the column of the pixel, `0` to `7`
+    /// * `y` - the row of the pixel, `0` to `7`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `x` or `y` is outside of the matrix
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn toggle_pixel(&mut self, addr: usize, x: u8, y: u8) -> Result<(), DataError> {
+        self.update_pixel(addr, x, y, |bits, mask| bits ^ mask)
+    }
+
+    ///
+    /// Writes a full 8x8 frame to the matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `frame` - the rows of pixels, see `write_row` for the bit layout
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_matrix(&mut self, addr: usize, frame: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
+        self.check_addr(addr)?;
+
+        let frame = self.layouts[addr].frame(frame);
+        for (row, bits) in (0..).zip(frame.iter()) {
+            self.write_row(addr, row, *bits)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes a full 8x8 frame to every connected matrix, sending
+    /// one row of all displays per transfer
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - one frame per display as connected in series (0 -> last)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if there is not exactly one frame per display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_matrix_all(&mut self, frames: &[[u8; MAX_DIGITS]]) -> Result<(), DataError> {
+        if frames.len() != self.devices {
+            return Err(DataError::InvalidLength);
+        }
+
+        let mut physical = [[0; MAX_DIGITS]; N];
+        for (addr, frame) in frames.iter().enumerate() {
+            physical[addr] = self.layouts[addr].frame(frame);
+        }
+
+        self.write_frames(&physical[..self.devices])
+    }
+
+    ///
+    /// Writes raw 8 row frames to every connected display, sending one
+    /// row of all displays per transfer so the whole chain is loaded
+    /// in 8 CS cycles. Unlike `write_matrix_all` no orientation or flips
+    /// are applied, frames are written to the digit registers as given.
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - one frame per display as connected in series (0 -> last),
+    ///   index 0 of each frame going to `Digit0`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if there is not exactly one frame per display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_frames(&mut self, frames: &[[u8; MAX_DIGITS]]) -> Result<(), DataError> {
+        if frames.len() != self.devices {
+            return Err(DataError::InvalidLength);
+        }
+        if self.buffered {
+            for (addr, frame) in frames.iter().enumerate() {
+                for (row, bits) in (0..).zip(frame.iter()) {
+                    self.write_row(addr, row, *bits)?;
+                }
+            }
+            return Ok(());
+        }
+
+        for row in 0..MAX_DIGITS {
+            frame::fill_row(&mut self.buffer, row, frames);
+            self.write_buffer()?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Draws a horizontal bar across the whole chain, lighting the columns from
+    /// the left in proportion to `value` of `max`. The chain is `8 * devices`
+    /// columns wide, display 0 being the leftmost.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - the progress, values at or above `max` light all columns
+    /// * `max` - the progress of a full bar
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn draw_progress(&mut self, value: u32, max: u32) -> Result<(), DataError> {
+        let width = (self.devices * MAX_DIGITS) as u64;
+        let lit = if value >= max {
+            width
+        } else {
+            value as u64 * width / max as u64
+        } as usize;
+
+        let mut frames = [[0; MAX_DIGITS]; N];
+        for (addr, frame) in frames.iter_mut().enumerate() {
+            let columns = lit.saturating_sub(addr * MAX_DIGITS).min(MAX_DIGITS);
+            *frame = [(0xFF00_u16 >> columns) as u8; MAX_DIGITS]; // leftmost columns on
+        }
+
+        self.write_matrix_all(&frames[..self.devices])
+    }
+
+    ///
+    /// Draws a bar graph across the whole chain, one bar per column growing up
+    /// from the bottom row. The chain is `8 * devices` columns wide, display 0
+    /// being the leftmost. Only the rows which changed are sent, see `flush`.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - the height of each bar from the leftmost column, `0` to `8`,
+    ///   higher values are clamped and columns without a value are blank
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if there are more values than columns
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn draw_bars(&mut self, values: &[u8]) -> Result<(), DataError> {
+        if values.len() > self.devices * MAX_DIGITS {
+            return Err(DataError::InvalidLength);
+        }
+
+        let mut frames = [[0; MAX_DIGITS]; N];
+        for (x, value) in values.iter().enumerate() {
+            let height = (*value as usize).min(MAX_DIGITS);
+            let frame = &mut frames[x / MAX_DIGITS];
+            for bits in frame[MAX_DIGITS - height..].iter_mut() {
+                *bits |= 0b1000_0000 >> (x % MAX_DIGITS);
+            }
+        }
+
+        // stage the frame so only changed rows are sent
+        let buffered = self.buffered;
+        self.buffered = true;
+        let result = self.write_matrix_all(&frames[..self.devices]);
+        self.buffered = buffered;
+        result?;
+
+        if buffered {
+            return Ok(());
+        }
+        self.flush()
+    }
+
+    ///
+    /// Sets whether given matrix shows the negative image, turning lit pixels
+    /// off and vice versa. Rows are inverted on the way to the chip only, so the
+    /// rows kept by the driver and `get_row` stay as written. The current rows
+    /// are sent again right away, or with the next `flush` while buffering.
+    /// Combines with `set_segment_polarity`, inverting a module with inverted
+    /// segment logic sends the rows as written.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `inverted` - whether to invert the rows
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_inverted(&mut self, addr: usize, inverted: bool) -> Result<(), DataError> {
+        self.check_addr(addr)?;
+
+        self.inverted[addr] = inverted;
+        for row in 0..MAX_DIGITS as u8 {
+            self.write_row(addr, row, self.digits[addr][row as usize])?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Starts buffering matrix writes. While buffering `write_row`, `set_pixel`,
+    /// `write_matrix` and `write_matrix_all` only change the frame kept in RAM,
+    /// nothing is sent until `flush` or `end_frame`. Other commands are still
+    /// sent right away.
+    ///
+    /// The frame is the row shadow the driver keeps anyway (8 bytes per display),
+    /// which `flush` compares to the rows as last sent to find the changed ones.
+    ///
+    pub fn begin_frame(&mut self) {
+        self.buffered = true;
+    }
+
+    ///
+    /// Sends the buffered frame to the displays and stops buffering
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn end_frame(&mut self) -> Result<(), DataError> {
+        self.buffered = false;
+        self.flush()
+    }
+
+    ///
+    /// Sends all rows which differ from what was last sent, one row of the whole
+    /// chain per transfer with no-ops for displays where that row is unchanged.
+    /// Nothing is sent if the frame did not change.
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn flush(&mut self) -> Result<(), DataError> {
+        let inversions = self.inversions();
+        for row in 0..MAX_DIGITS {
+            let mut changed = false;
+            let displays = self.digits.iter().zip(self.sent.iter());
+            let displays = displays.zip(inversions.iter());
+            for (((digits, sent), inverted), pair) in displays.zip(self.buffer.iter_mut()) {
+                // compare as sent on the wire, inverting changes every row
+                let wire = if *inverted { !digits[row] } else { digits[row] };
+                *pair = if wire != sent[row] {
+                    changed = true;
+                    [row as u8 + 1, digits[row]]
+                } else {
+                    [0, 0]
+                };
+            }
+
+            if changed {
+                self.write_buffer()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Sends every row of all displays regardless of what was last sent,
+    /// restoring the frame after the displays lost it, e.g. due to a glitch
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn force_flush(&mut self) -> Result<(), DataError> {
+        for row in 0..MAX_DIGITS {
+            for (pair, digits) in self.buffer.iter_mut().zip(self.digits.iter()) {
+                *pair = [row as u8 + 1, digits[row]];
+            }
+
+            self.write_buffer()?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Sets the orientation of given matrix, applied by all following
+    /// frame and pixel writes
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `orientation` - the clockwise rotation of the content
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    pub fn set_orientation(
+        &mut self,
+        addr: usize,
+        orientation: Orientation,
+    ) -> Result<(), DataError> {
+        self.check_addr(addr)?;
+
+        self.layouts[addr].orientation = orientation;
+        Ok(())
+    }
+
+    ///
+    /// Sets whether given matrix is mirrored, applied by all following
+    /// frame and pixel writes after the orientation
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `flip_h` - whether to mirror columns, reversing the bits of each row
+    /// * `flip_v` - whether to mirror rows, reversing which register each row goes to
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    ///
+    pub fn set_flip(&mut self, addr: usize, flip_h: bool, flip_v: bool) -> Result<(), DataError> {
+        self.check_addr(addr)?;
+
+        self.layouts[addr].flip_h = flip_h;
+        self.layouts[addr].flip_v = flip_v;
+        Ok(())
+    }
+
+    // read-modify-writes the row holding given pixel using its column mask
+    fn update_pixel<F>(&mut self, addr: usize, x: u8, y: u8, update: F) -> Result<(), DataError>
+    where
+        F: FnOnce(u8, u8) -> u8,
+    {
+        if x as usize >= MAX_DIGITS || y as usize >= MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+        self.check_addr(addr)?;
+
+        let (x, y) = self.layouts[addr].pixel(x, y);
+
+        let mask = 0b1000_0000 >> x;
+        let bits = update(self.digits[addr][y as usize], mask);
+
+        self.write_row(addr, y, bits)
+    }
+}
+
+impl<CONNECTOR, const N: usize> MatrixWrite for MAX7219<CONNECTOR, N>
+where
+    CONNECTOR: Connector,
+{
+    fn set_pixel(&mut self, addr: usize, x: u8, y: u8, on: bool) -> Result<(), DataError> {
+        MAX7219::set_pixel(self, addr, x, y, on)
+    }
+
+    fn flush(&mut self) -> Result<(), DataError> {
+        MAX7219::flush(self)
+    }
+}
+
+///
+/// Transposes a frame over its top left to bottom right diagonal,
+/// turning each row into the column of the same index
+///
+pub fn transpose_frame(frame: &[u8; MAX_DIGITS]) -> [u8; MAX_DIGITS] {
+    let mut result = [0; MAX_DIGITS];
+    for (y, bits) in frame.iter().enumerate() {
+        for (x, column) in result.iter_mut().enumerate() {
+            if bits & (0b1000_0000 >> x) > 0 {
+                *column |= 0b1000_0000 >> y;
+            }
+        }
+    }
+
+    result
+}
+
+///
+/// Mirrors a frame left to right, reversing the bits of each row
+///
+pub fn mirror_frame(frame: &[u8; MAX_DIGITS]) -> [u8; MAX_DIGITS] {
+    let mut result = *frame;
+    for bits in result.iter_mut() {
+        *bits = bits.reverse_bits();
+    }
+
+    result
+}
+
+///
+/// Rotates a frame 90 degrees clockwise, the same as writing it
+/// to a matrix set to `Orientation::Rotate90`
+///
+pub fn rotate_frame_90(frame: &[u8; MAX_DIGITS]) -> [u8; MAX_DIGITS] {
+    orient_frame(Orientation::Rotate90, frame)
+}
+
+///
+/// Translate a logical pixel position into the physical
+/// position on a matrix mounted with given orientation.
+///
+fn orient_pixel(orientation: Orientation, x: u8, y: u8) -> (u8, u8) {
+    let max = MAX_DIGITS as u8 - 1;
+
+    match orientation {
+        Orientation::Normal => (x, y),
+        Orientation::Rotate90 => (max - y, x),
+        Orientation::Rotate180 => (max - x, max - y),
+        Orientation::Rotate270 => (y, max - x),
+    }
+}
+
+///
+/// Translate a logical frame into the physical rows
+/// of a matrix mounted with given orientation.
+///
+fn orient_frame(orientation: Orientation, frame: &[u8; MAX_DIGITS]) -> [u8; MAX_DIGITS] {
+    if let Orientation::Normal = orientation {
+        return *frame;
+    }
+
+    let mut result = [0; MAX_DIGITS];
+    for y in 0..MAX_DIGITS as u8 {
+        for x in 0..MAX_DIGITS as u8 {
+            if frame[y as usize] & (0b1000_0000 >> x) > 0 {
+                let (px, py) = orient_pixel(orientation, x, y);
+                result[py as usize] |= 0b1000_0000 >> px;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{display, Display};
+
+    // returns all physical rows of given display
+    fn rows(display: &Display, addr: usize) -> [u8; MAX_DIGITS] {
+        let mut rows = [0; MAX_DIGITS];
+        for (row, bits) in (0..).zip(rows.iter_mut()) {
+            *bits = display.get_row(addr, row).unwrap();
+        }
+        rows
+    }
+
+    #[test]
+    fn orients_pixels_and_frames() {
+        // pixel (1, 0) as seen on the matrix: physical register and bits of its row
+        let expected = [
+            (Orientation::Normal, 1, 0b0100_0000),
+            (Orientation::Rotate90, 2, 0b0000_0001),
+            (Orientation::Rotate180, 8, 0b0000_0010),
+            (Orientation::Rotate270, 7, 0b1000_0000),
+        ];
+
+        for (orientation, register, bits) in expected.iter() {
+            let mut pixel = display(1);
+            pixel.set_orientation(0, *orientation).unwrap();
+            pixel.set_pixel(0, 1, 0, true).unwrap();
+            assert!(pixel
+                .connector()
+                .commands()
+                .eq([(0, *register, *bits)].iter().copied()));
+
+            // a frame with the same pixel lands on the same physical row
+            let mut framed = display(1);
+            framed.set_orientation(0, *orientation).unwrap();
+            framed
+                .write_matrix(0, &[0b0100_0000, 0, 0, 0, 0, 0, 0, 0])
+                .unwrap();
+            assert_eq!(rows(&framed, 0), rows(&pixel, 0));
+        }
+    }
+
+    #[test]
+    fn flips_rows_and_columns() {
+        assert_eq!(0b1100_0001_u8.reverse_bits(), 0b1000_0011);
+        assert_eq!(0b0000_0001_u8.reverse_bits(), 0b1000_0000);
+
+        let frame = [0b1100_0000, 0b0010_0000, 0, 0, 0, 0, 0, 0b0000_0001];
+        let expected = [
+            (false, false, frame),
+            (
+                true,
+                false,
+                [0b0000_0011, 0b0000_0100, 0, 0, 0, 0, 0, 0b1000_0000],
+            ),
+            (
+                false,
+                true,
+                [0b0000_0001, 0, 0, 0, 0, 0, 0b0010_0000, 0b1100_0000],
+            ),
+            (
+                true,
+                true,
+                [0b1000_0000, 0, 0, 0, 0, 0, 0b0000_0100, 0b0000_0011],
+            ),
+        ];
+
+        for (flip_h, flip_v, physical) in expected.iter() {
+            let mut framed = display(1);
+            framed.set_flip(0, *flip_h, *flip_v).unwrap();
+            framed.write_matrix(0, &frame).unwrap();
+            assert_eq!(rows(&framed, 0), *physical);
+
+            // pixels are flipped the same way
+            let mut pixels = display(1);
+            pixels.set_flip(0, *flip_h, *flip_v).unwrap();
+            for (y, bits) in (0..).zip(frame.iter()) {
+                for x in 0..MAX_DIGITS as u8 {
+                    if bits & (0b1000_0000 >> x) > 0 {
+                        pixels.set_pixel(0, x, y, true).unwrap();
+                    }
+                }
+            }
+            assert_eq!(rows(&pixels, 0), *physical);
+        }
+    }
+
+    #[test]
+    fn pixels_keep_the_rest_of_the_row() {
+        let mut display = display(1);
+
+        for x in [1, 4, 6].iter() {
+            display.set_pixel(0, *x, 1, true).unwrap();
+        }
+        assert_eq!(display.get_row(0, 1), Ok(0b0100_1010));
+        assert_eq!(
+            display.connector().commands().last(),
+            Some((0, 2, 0b0100_1010))
+        );
+
+        display.clear_pixel(0, 4, 1).unwrap();
+        assert_eq!(display.get_row(0, 1), Ok(0b0100_0010));
+        display.toggle_pixel(0, 6, 1).unwrap();
+        display.toggle_pixel(0, 0, 1).unwrap();
+        assert_eq!(display.get_row(0, 1), Ok(0b1100_0000));
+    }
+
+    #[test]
+    fn writes_frames_one_row_of_the_chain_per_frame() {
+        let mut display = display(2);
+        let frames = [
+            [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+            [0x80; MAX_DIGITS],
+        ];
+
+        display.write_frames(&frames).unwrap();
+
+        assert_eq!(display.connector().frame_count(), MAX_DIGITS);
+        for (row, (first, second)) in frames[0].iter().zip(frames[1].iter()).enumerate() {
+            let header = row as u8 + 1;
+            let frame = [header, *first, header, *second];
+            assert_eq!(display.connector().frame(row), Some(&frame[..]));
+        }
+    }
+
+    #[test]
+    fn flushes_changed_rows_only() {
+        let mut display = display(2);
+
+        display.begin_frame();
+        display.write_row(1, 2, 0x3C).unwrap();
+        assert_eq!(display.connector().frame_count(), 0);
+
+        display.flush().unwrap();
+        assert_eq!(display.connector().frame_count(), 1);
+        assert_eq!(display.connector().frame(0), Some(&[0, 0, 0x03, 0x3C][..]));
+
+        display.connector_mut().clear();
+        display.flush().unwrap();
+        assert_eq!(display.connector().frame_count(), 0);
+
+        display.force_flush().unwrap();
+        assert_eq!(display.connector().frame_count(), MAX_DIGITS);
+    }
+
+    #[test]
+    fn transforms_asymmetric_sprite() {
+        // an F in the top left corner, different under every transform
+        let sprite = [0b1110_0000, 0b1000_0000, 0b1100_0000, 0, 0, 0, 0, 0];
+
+        let transposed = transpose_frame(&sprite);
+        assert_eq!(
+            transposed,
+            [0b1110_0000, 0b1010_0000, 0b1000_0000, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(transpose_frame(&transposed), sprite);
+
+        let mirrored = mirror_frame(&sprite);
+        assert_eq!(
+            mirrored,
+            [0b0000_0111, 0b0000_0001, 0b0000_0011, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(mirror_frame(&mirrored), sprite);
+
+        let rotated = rotate_frame_90(&sprite);
+        assert_eq!(
+            rotated,
+            [0b0000_0111, 0b0000_0101, 0b0000_0001, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(rotated, mirror_frame(&transposed));
+
+        let mut turned = rotated;
+        for _ in 0..3 {
+            turned = rotate_frame_90(&turned);
+        }
+        assert_eq!(turned, sprite);
+    }
+
+    #[test]
+    fn draws_bars_from_bottom_row() {
+        let mut display = display(2);
+
+        // heights 0, 8, clamped 9 and 3, then 1 in the first column of display 1
+        display.draw_bars(&[0, 8, 9, 3, 0, 0, 0, 0, 1]).unwrap();
+        assert_eq!(
+            rows(&display, 0),
+            [0x60, 0x60, 0x60, 0x60, 0x60, 0x70, 0x70, 0x70]
+        );
+        assert_eq!(rows(&display, 1), [0, 0, 0, 0, 0, 0, 0, 0x80]);
+
+        // unchanged rows are not sent again
+        display.connector_mut().clear();
+        display.draw_bars(&[0, 8, 9, 2]).unwrap();
+        assert_eq!(display.connector().frame_count(), 2);
+        assert_eq!(rows(&display, 0)[5], 0x60);
+        assert_eq!(rows(&display, 1), [0; MAX_DIGITS]);
+
+        assert_eq!(
+            display.draw_bars(&[1; 17]).err(),
+            Some(DataError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn draws_progress_rounding_down_to_columns() {
+        let mut display = display(2);
+
+        display.draw_progress(0, 100).unwrap();
+        assert_eq!(rows(&display, 0), [0; MAX_DIGITS]);
+        assert_eq!(rows(&display, 1), [0; MAX_DIGITS]);
+
+        // 16 columns: a third lights 5.33 and two thirds 10.67 of them
+        display.draw_progress(1, 3).unwrap();
+        assert_eq!(rows(&display, 0), [0xF8; MAX_DIGITS]);
+        assert_eq!(rows(&display, 1), [0; MAX_DIGITS]);
+        display.draw_progress(2, 3).unwrap();
+        assert_eq!(rows(&display, 0), [0xFF; MAX_DIGITS]);
+        assert_eq!(rows(&display, 1), [0xC0; MAX_DIGITS]);
+
+        for (value, max) in [(100, 100), (150, 100), (0, 0)].iter() {
+            display.draw_progress(0, 1).unwrap();
+            display.draw_progress(*value, *max).unwrap();
+            assert_eq!(rows(&display, 0), [0xFF; MAX_DIGITS]);
+            assert_eq!(rows(&display, 1), [0xFF; MAX_DIGITS]);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Recording connector for testing code driving the MAX7219
+//!
+//! Enabled by the `mock` feature. `RecordingConnector` keeps every frame
+//! sent to it in a fixed-size buffer instead of talking to hardware,
+//! so tests can assert the exact command sequence. `WireRecorder` records
+//! the bit-banged pins instead, to assert the wire protocol itself. Its pins
+//! also implement the `embedded-hal` 1.0 `OutputPin` with the `eh1` feature.
+
+use core::cell::{Ref, RefCell};
+use core::convert::Infallible;
+
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::connectors::Connector;
+use crate::DataError;
+
+///
+/// Connector capturing all frames sent to it, up to `CAPACITY` bytes.
+/// Frames sent by a `MAX7219` always hold one header and data pair
+/// per connected display, so all captured frames have the same length.
+///
+pub struct RecordingConnector<const CAPACITY: usize = 1024> {
+    data: [u8; CAPACITY],
+    len: usize,
+    frame_len: usize,
+}
+
+impl<const CAPACITY: usize> RecordingConnector<CAPACITY> {
+    ///
+    /// Construct a new connector with nothing recorded
+    ///
+    pub fn new() -> Self {
+        RecordingConnector {
+            data: [0; CAPACITY],
+            len: 0,
+            frame_len: 0,
+        }
+    }
+
+    ///
+    /// Returns all bytes recorded so far
+    ///
+    pub fn bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    ///
+    /// Returns the number of frames recorded so far. Every frame is one
+    /// CS (LOAD) cycle on real connectors, allowing to assert how often
+    /// the displays latched.
+    ///
+    pub fn frame_count(&self) -> usize {
+        self.len.checked_div(self.frame_len).unwrap_or(0)
+    }
+
+    ///
+    /// Returns given recorded frame, first one being `0`
+    ///
+    pub fn frame(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.frame_count() {
+            return None;
+        }
+
+        let start = index * self.frame_len;
+        Some(&self.data[start..start + self.frame_len])
+    }
+
+    ///
+    /// Decodes the recorded frames into `(addr, register, value)` commands in the
+    /// order they were sent, `addr` being the display as connected in series (0 -> last).
+    /// No-ops sent to displays which were not addressed are skipped.
+    ///
+    pub fn commands(&self) -> impl Iterator<Item = (usize, u8, u8)> + '_ {
+        let devices = self.frame_len / 2;
+
+        self.bytes()
+            .chunks(2)
+            .enumerate()
+            .map(move |(i, pair)| (i % devices, pair[0], pair[1]))
+            .filter(|(_, register, _)| *register != 0x00)
+    }
+
+    ///
+    /// Forgets everything recorded so far
+    ///
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.frame_len = 0;
+    }
+}
+
+impl<const CAPACITY: usize> Default for RecordingConnector<CAPACITY> {
+    fn default() -> Self {
+        RecordingConnector::new()
+    }
+}
+
+impl<const CAPACITY: usize> Connector for RecordingConnector<CAPACITY> {
+    ///
+    /// Records given frame
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::Overflow` - returned if the frame does not fit the remaining capacity
+    /// * `DataError::InvalidLength` - returned if the frame is not as long as the recorded ones
+    ///
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), DataError> {
+        if self.len + frame.len() > CAPACITY {
+            return Err(DataError::Overflow);
+        }
+        if self.frame_len != 0 && frame.len() != self.frame_len {
+            return Err(DataError::InvalidLength);
+        }
+
+        self.data[self.len..self.len + frame.len()].copy_from_slice(frame);
+        self.len += frame.len();
+        self.frame_len = frame.len();
+
+        Ok(())
+    }
+}
+
+///
+/// Records the bits shifted out over bit-banged pins, sampling DATA on every
+/// rising SCK edge like the MAX7219 does, up to `CAPACITY` bytes. Hand the pins
+/// from `data_pin`, `cs_pin` and `sck_pin` to `MAX7219::from_pins` to assert e.g.
+/// that every byte takes exactly 8 clock pulses and is sent MSB first.
+/// SCK edges while CS is high are ignored, such as the clock moving to its
+/// idle level between frames.
+///
+pub struct WireRecorder<const CAPACITY: usize = 1024> {
+    state: RefCell<WireState<CAPACITY>>,
+}
+
+struct WireState<const CAPACITY: usize> {
+    levels: [bool; 3],
+    bytes: [u8; CAPACITY],
+    len: usize,
+    bits: u8,
+    bit_count: usize,
+    edges: usize,
+    falling: usize,
+    loads: usize,
+}
+
+#[derive(Clone, Copy)]
+enum WireLine {
+    Data = 0,
+    Cs = 1,
+    Sck = 2,
+}
+
+impl<const CAPACITY: usize> WireRecorder<CAPACITY> {
+    ///
+    /// Construct a new recorder with nothing recorded and all pins low
+    ///
+    pub fn new() -> Self {
+        WireRecorder {
+            state: RefCell::new(WireState {
+                levels: [false; 3],
+                bytes: [0; CAPACITY],
+                len: 0,
+                bits: 0,
+                bit_count: 0,
+                edges: 0,
+                falling: 0,
+                loads: 0,
+            }),
+        }
+    }
+
+    ///
+    /// Returns the pin to use as DATA
+    ///
+    pub fn data_pin(&self) -> WirePin<'_, CAPACITY> {
+        WirePin {
+            recorder: self,
+            line: WireLine::Data,
+        }
+    }
+
+    ///
+    /// Returns the pin to use as CS (LOAD)
+    ///
+    pub fn cs_pin(&self) -> WirePin<'_, CAPACITY> {
+        WirePin {
+            recorder: self,
+            line: WireLine::Cs,
+        }
+    }
+
+    ///
+    /// Returns the pin to use as SCK
+    ///
+    pub fn sck_pin(&self) -> WirePin<'_, CAPACITY> {
+        WirePin {
+            recorder: self,
+            line: WireLine::Sck,
+        }
+    }
+
+    ///
+    /// Returns the bytes assembled MSB first from the sampled bits so far,
+    /// bytes beyond `CAPACITY` being dropped
+    ///
+    pub fn bytes(&self) -> Ref<'_, [u8]> {
+        Ref::map(self.state.borrow(), |state| &state.bytes[..state.len])
+    }
+
+    ///
+    /// Returns the number of rising SCK edges so far, 8 per shifted byte
+    ///
+    pub fn rising_edges(&self) -> usize {
+        self.state.borrow().edges
+    }
+
+    ///
+    /// Returns the number of falling SCK edges so far
+    ///
+    pub fn falling_edges(&self) -> usize {
+        self.state.borrow().falling
+    }
+
+    ///
+    /// Returns whether SCK is currently high, which is its idle level between frames
+    ///
+    pub fn sck_high(&self) -> bool {
+        self.state.borrow().levels[WireLine::Sck as usize]
+    }
+
+    ///
+    /// Returns the number of bits sampled since the last complete byte,
+    /// which is `0` whenever only whole bytes were shifted out
+    ///
+    pub fn partial_bits(&self) -> usize {
+        self.state.borrow().bit_count
+    }
+
+    ///
+    /// Returns the number of rising CS edges so far, each latching a frame
+    ///
+    pub fn load_count(&self) -> usize {
+        self.state.borrow().loads
+    }
+
+    ///
+    /// Forgets everything recorded so far, keeping the current pin levels
+    ///
+    pub fn clear(&self) {
+        let mut state = self.state.borrow_mut();
+        state.len = 0;
+        state.bits = 0;
+        state.bit_count = 0;
+        state.edges = 0;
+        state.falling = 0;
+        state.loads = 0;
+    }
+
+    // records the level of given line, sampling DATA on rising clock edges
+    fn set_level(&self, line: WireLine, high: bool) {
+        let mut state = self.state.borrow_mut();
+        let rising = high && !state.levels[line as usize];
+        let falling = !high && state.levels[line as usize];
+        state.levels[line as usize] = high;
+        let selected = !state.levels[WireLine::Cs as usize];
+
+        match line {
+            WireLine::Sck if selected && falling => state.falling += 1,
+            WireLine::Sck if selected && rising => {
+                state.edges += 1;
+                state.bits = (state.bits << 1) | state.levels[WireLine::Data as usize] as u8;
+                state.bit_count += 1;
+                if state.bit_count == 8 {
+                    if state.len < CAPACITY {
+                        let len = state.len;
+                        state.bytes[len] = state.bits;
+                        state.len += 1;
+                    }
+                    state.bits = 0;
+                    state.bit_count = 0;
+                }
+            }
+            WireLine::Cs if rising => state.loads += 1,
+            _ => {}
+        }
+    }
+}
+
+impl<const CAPACITY: usize> Default for WireRecorder<CAPACITY> {
+    fn default() -> Self {
+        WireRecorder::new()
+    }
+}
+
+///
+/// Output pin of a `WireRecorder`
+///
+pub struct WirePin<'a, const CAPACITY: usize> {
+    recorder: &'a WireRecorder<CAPACITY>,
+    line: WireLine,
+}
+
+impl<'a, const CAPACITY: usize> OutputPin for WirePin<'a, CAPACITY> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.recorder.set_level(self.line, false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.recorder.set_level(self.line, true);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl<'a, const CAPACITY: usize> embedded_hal_1::digital::ErrorType for WirePin<'a, CAPACITY> {
+    type Error = Infallible;
+}
+
+#[cfg(feature = "eh1")]
+impl<'a, const CAPACITY: usize> embedded_hal_1::digital::OutputPin for WirePin<'a, CAPACITY> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.recorder.set_level(self.line, false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.recorder.set_level(self.line, true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_frames_into_commands() {
+        let mut connector: RecordingConnector = RecordingConnector::new();
+        // a broadcast to all three displays, then a single write padded with no-ops
+        connector.write_frame(&[0x0C, 1, 0x0C, 1, 0x0C, 1]).unwrap();
+        connector.write_frame(&[0, 0, 0x03, 0x42, 0, 0]).unwrap();
+
+        assert_eq!(connector.frame_count(), 2);
+        assert_eq!(connector.frame(1), Some(&[0, 0, 0x03, 0x42, 0, 0][..]));
+        assert_eq!(connector.frame(2), None);
+        assert_eq!(connector.bytes().len(), 12);
+        assert!(connector.commands().eq([
+            (0, 0x0C, 1),
+            (1, 0x0C, 1),
+            (2, 0x0C, 1),
+            (1, 0x03, 0x42)
+        ]
+        .iter()
+        .copied()));
+
+        assert_eq!(
+            connector.write_frame(&[0x01, 0x01]).err(),
+            Some(DataError::InvalidLength)
+        );
+        connector.clear();
+        assert_eq!(connector.commands().count(), 0);
+        connector.write_frame(&[0x01, 0x01]).unwrap();
+        assert!(connector.commands().eq([(0, 0x01, 0x01)].iter().copied()));
+    }
+
+    #[test]
+    fn rejects_frames_beyond_capacity() {
+        let mut connector: RecordingConnector<4> = RecordingConnector::new();
+        connector.write_frame(&[0x01, 0x01, 0x02, 0x02]).unwrap();
+        assert_eq!(
+            connector.write_frame(&[0x01, 0x01, 0x02, 0x02]).err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(connector.frame_count(), 1);
+    }
+}
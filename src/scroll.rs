@@ -0,0 +1,444 @@
+//! Scrolling text and large glyphs for chains of 8x8 LED matrices
+//!
+//! Text is rendered column by column from 5x7 glyphs with a blank column
+//! between characters. The visible window spans all connected displays,
+//! column 0 being the leftmost column of display 0 (last in series).
+//! `BigFont` glyphs use the same column layout spanning several displays.
+
+use crate::connectors::Connector;
+use crate::{DataError, MAX7219, MAX_DIGITS};
+
+/// Width of a glyph in columns
+pub const GLYPH_WIDTH: usize = 5;
+
+///
+/// Function looking up the columns of a 5x7 glyph for given character.
+/// Bit 0 of each column is the top row. Characters without
+/// a glyph (`None`) are rendered blank. The `font` feature
+/// provides a built-in ASCII font as `font::glyph`.
+///
+pub type GlyphFn = fn(char) -> Option<[u8; GLYPH_WIDTH]>;
+
+///
+/// Font of glyphs wider than a single matrix, shown by `MAX7219::write_big_char`.
+/// Glyphs are 8 pixels high and given as columns from the left, bit 0 of each
+/// column being the top row like `GlyphFn`.
+///
+#[derive(Clone, Copy)]
+pub struct BigFont {
+    /// Width of every glyph in columns
+    pub width: usize,
+    /// Looks up the `width` columns of given character, `None` shows blank
+    pub glyph: fn(char) -> Option<&'static [u8]>,
+}
+
+///
+/// Direction the text moves in across the chain
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    /// Enters from the right edge and leaves on the left edge
+    Left,
+    /// Enters from the left edge and leaves on the right edge
+    Right,
+}
+
+///
+/// Scrolls a text across the whole chain, one column per `step`. By default the text
+/// moves to the left, entering from the right edge, and is done once it fully left
+/// on the left edge. See `set_direction` to scroll the other way.
+///
+pub struct Scroller<'a> {
+    text: &'a [u8],
+    glyph: GlyphFn,
+    offset: usize,
+    direction: ScrollDirection,
+}
+
+impl<'a> Scroller<'a> {
+    ///
+    /// Construct a new scroller for given text
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - the text to scroll, rendered byte by byte so non-ASCII characters show blank
+    /// * `glyph` - the glyph lookup function of the font to use
+    ///
+    pub fn new(text: &'a str, glyph: GlyphFn) -> Self {
+        Scroller {
+            text: text.as_bytes(),
+            glyph,
+            offset: 0,
+            direction: ScrollDirection::Left,
+        }
+    }
+
+    ///
+    /// Replaces the text, continuing from the current position. The text
+    /// enters again from the edge once the position is past the new text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - the text to scroll, rendered byte by byte so non-ASCII characters show blank
+    ///
+    pub fn set_text(&mut self, text: &'a str) {
+        self.text = text.as_bytes();
+    }
+
+    ///
+    /// Sets the direction the text moves in, to the left by default.
+    /// Takes effect with the next `step`, continuing from the current position.
+    ///
+    pub fn set_direction(&mut self, direction: ScrollDirection) {
+        self.direction = direction;
+    }
+
+    ///
+    /// Moves the text one column in the scroll direction and writes the visible window
+    /// to the displays. Returns `true` once the text has fully scrolled off the chain,
+    /// the following step starts over from the edge the text enters from.
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - the driver of the matrix chain to write to
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn step<CONNECTOR, const N: usize>(
+        &mut self,
+        display: &mut MAX7219<CONNECTOR, N>,
+    ) -> Result<bool, DataError>
+    where
+        CONNECTOR: Connector,
+    {
+        let width = display.pixel_width();
+        let end = width + self.text_width();
+        if self.offset >= end {
+            self.offset = 0;
+        }
+        self.offset += 1;
+
+        let mut frames = [[0; MAX_DIGITS]; N];
+        for x in 0..width {
+            // the window slides over the canvas to the right when moving the text left
+            let column = match self.direction {
+                ScrollDirection::Left => self.column(self.offset + x, width),
+                ScrollDirection::Right => self.column(end + x - self.offset, width),
+            };
+            draw_column(&mut frames, x, column);
+        }
+
+        display.write_matrix_all(&frames[..display.devices])?;
+
+        Ok(self.offset >= end)
+    }
+
+    ///
+    /// Starts the text over from the edge it enters from on next `step`
+    ///
+    pub fn reset(&mut self) {
+        self.offset = 0;
+    }
+
+    // width of the rendered text in columns including character spacing
+    fn text_width(&self) -> usize {
+        self.text.len() * (GLYPH_WIDTH + 1)
+    }
+
+    // column of the virtual canvas, which is the blank window followed by the text
+    fn column(&self, x: usize, width: usize) -> u8 {
+        if x < width || x >= width + self.text_width() {
+            return 0;
+        }
+
+        let x = x - width;
+        let (index, col) = (x / (GLYPH_WIDTH + 1), x % (GLYPH_WIDTH + 1));
+        if col == GLYPH_WIDTH {
+            return 0; // spacing
+        }
+
+        match (self.glyph)(self.text[index] as char) {
+            Some(columns) => columns[col],
+            None => 0,
+        }
+    }
+}
+
+///
+/// Scrolls a text repeatedly at a fixed speed, keeping track of the timing so
+/// it can be updated from a main loop or timer callback as often as convenient
+///
+pub struct Marquee<'a> {
+    scroller: Scroller<'a>,
+    interval_ms: u32,
+    gap: u32,
+    pause: u32,
+    last_ms: Option<u32>,
+}
+
+impl<'a> Marquee<'a> {
+    ///
+    /// Construct a new marquee scrolling given text to the left
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - the text to scroll, rendered byte by byte so non-ASCII characters show blank
+    /// * `glyph` - the glyph lookup function of the font to use
+    /// * `interval_ms` - time between moving the text by one column
+    /// * `gap` - number of intervals the chain stays blank before the text enters again
+    ///
+    pub fn new(text: &'a str, glyph: GlyphFn, interval_ms: u32, gap: u32) -> Self {
+        Marquee {
+            scroller: Scroller::new(text, glyph),
+            interval_ms,
+            gap,
+            pause: 0,
+            last_ms: None,
+        }
+    }
+
+    ///
+    /// Sets the direction the text moves in, see `Scroller::set_direction`
+    ///
+    pub fn set_direction(&mut self, direction: ScrollDirection) {
+        self.scroller.set_direction(direction);
+    }
+
+    ///
+    /// Replaces the text shown, either starting it over from the edge
+    /// or continuing from the current position
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - the text to scroll, rendered byte by byte so non-ASCII characters show blank
+    /// * `restart` - whether the new text enters from the edge again
+    ///
+    pub fn set_text(&mut self, text: &'a str, restart: bool) {
+        self.scroller.set_text(text);
+        if restart {
+            self.scroller.reset();
+            self.pause = 0;
+        }
+    }
+
+    ///
+    /// Moves the text by one column if the interval passed since the last move,
+    /// doing nothing otherwise. The first call always moves the text.
+    /// Returns `true` if the displays were written to.
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - the driver of the matrix chain to write to
+    /// * `now_ms` - the current time in milliseconds, allowed to wrap around
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn update<CONNECTOR, const N: usize>(
+        &mut self,
+        display: &mut MAX7219<CONNECTOR, N>,
+        now_ms: u32,
+    ) -> Result<bool, DataError>
+    where
+        CONNECTOR: Connector,
+    {
+        if let Some(last_ms) = self.last_ms {
+            if now_ms.wrapping_sub(last_ms) < self.interval_ms {
+                return Ok(false);
+            }
+        }
+        self.last_ms = Some(now_ms);
+
+        // the chain is blank once the text left it, wait out the gap
+        if self.pause > 0 {
+            self.pause -= 1;
+            return Ok(false);
+        }
+
+        if self.scroller.step(display)? {
+            self.pause = self.gap;
+        }
+
+        Ok(true)
+    }
+}
+
+impl<CONNECTOR, const N: usize> MAX7219<CONNECTOR, N>
+where
+    CONNECTOR: Connector,
+{
+    ///
+    /// Writes a single glyph of a big font centered across the whole chain,
+    /// blanking the columns around it
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - the character to show
+    /// * `font` - the font to look the glyph up in
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if the glyph is wider than the chain
+    ///   or does not have as many columns as the font is wide
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_big_char(&mut self, c: char, font: &BigFont) -> Result<(), DataError> {
+        let width = self.pixel_width();
+        if font.width > width {
+            return Err(DataError::InvalidLength);
+        }
+
+        let columns: &[u8] = match (font.glyph)(c) {
+            Some(columns) if columns.len() != font.width => return Err(DataError::InvalidLength),
+            Some(columns) => columns,
+            None => &[],
+        };
+
+        let mut frames = [[0; MAX_DIGITS]; N];
+        for (x, column) in ((width - font.width) / 2..).zip(columns.iter()) {
+            draw_column(&mut frames, x, *column);
+        }
+
+        self.write_matrix_all(&frames[..self.devices])
+    }
+}
+
+// lights the pixels of a column, bit 0 being the top row, at chain-wide column x
+fn draw_column(frames: &mut [[u8; MAX_DIGITS]], x: usize, column: u8) {
+    let frame = &mut frames[x / MAX_DIGITS];
+    for (row, bits) in frame.iter_mut().enumerate() {
+        if column & (1 << row) > 0 {
+            *bits |= 0b1000_0000 >> (x % MAX_DIGITS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{display, Display};
+
+    // glyph lighting the top pixel of its first column and the second pixel of its second
+    fn corner(c: char) -> Option<[u8; GLYPH_WIDTH]> {
+        match c {
+            'A' => Some([0b01, 0b10, 0, 0, 0]),
+            _ => None,
+        }
+    }
+
+    // (top row, second row) of given display
+    fn top_rows(display: &Display, addr: usize) -> (u8, u8) {
+        (
+            display.get_row(addr, 0).unwrap(),
+            display.get_row(addr, 1).unwrap(),
+        )
+    }
+
+    #[test]
+    fn scrolls_left_and_wraps_around() {
+        let mut display = display(2);
+        let mut scroller = Scroller::new("A", corner);
+        // the text enters at the right edge of display 1
+        assert_eq!(scroller.step(&mut display), Ok(false));
+        assert_eq!(top_rows(&display, 1), (0b0000_0001, 0));
+        assert_eq!(top_rows(&display, 0), (0, 0));
+        assert_eq!(scroller.step(&mut display), Ok(false));
+        assert_eq!(top_rows(&display, 1), (0b0000_0010, 0b0000_0001));
+
+        // and crosses over to display 0
+        for _ in 2..9 {
+            assert_eq!(scroller.step(&mut display), Ok(false));
+        }
+        assert_eq!(top_rows(&display, 1), (0, 0b1000_0000));
+        assert_eq!(top_rows(&display, 0), (0b0000_0001, 0));
+
+        // done once the text including its spacing left the chain
+        for _ in 9..21 {
+            assert_eq!(scroller.step(&mut display), Ok(false));
+        }
+        assert_eq!(scroller.step(&mut display), Ok(true));
+        assert_eq!(top_rows(&display, 0), (0, 0));
+        assert_eq!(top_rows(&display, 1), (0, 0));
+
+        // the next step starts over at the right edge
+        assert_eq!(scroller.step(&mut display), Ok(false));
+        assert_eq!(top_rows(&display, 1), (0b0000_0001, 0));
+    }
+
+    #[test]
+    fn scrolls_right_and_wraps_around() {
+        let mut display = display(1);
+        let mut scroller = Scroller::new("A", corner);
+        scroller.set_direction(ScrollDirection::Right);
+
+        // the last columns of the text enter first at the left edge
+        for _ in 0..4 {
+            assert_eq!(scroller.step(&mut display), Ok(false));
+            assert_eq!(top_rows(&display, 0), (0, 0));
+        }
+        assert_eq!(scroller.step(&mut display), Ok(false));
+        assert_eq!(top_rows(&display, 0), (0, 0b1000_0000));
+        assert_eq!(scroller.step(&mut display), Ok(false));
+        assert_eq!(top_rows(&display, 0), (0b1000_0000, 0b0100_0000));
+
+        for _ in 7..14 {
+            assert_eq!(scroller.step(&mut display), Ok(false));
+        }
+        assert_eq!(top_rows(&display, 0), (0b0000_0001, 0));
+        assert_eq!(scroller.step(&mut display), Ok(true));
+        assert_eq!(top_rows(&display, 0), (0, 0));
+
+        scroller.step(&mut display).unwrap();
+        assert_eq!(top_rows(&display, 0), (0, 0));
+        for _ in 1..5 {
+            scroller.step(&mut display).unwrap();
+        }
+        assert_eq!(top_rows(&display, 0), (0, 0b1000_0000));
+    }
+
+    #[test]
+    fn marquee_steps_per_interval_and_waits_out_gap() {
+        let mut display = display(1);
+        let mut marquee = Marquee::new("A", corner, 10, 2);
+        // a frame writes all 8 rows of the display
+        let frames = |display: &Display| display.connector().frame_count() / MAX_DIGITS;
+
+        // the first update always moves, then once per interval even across a wrap
+        let start = u32::MAX - 4;
+        assert_eq!(marquee.update(&mut display, start), Ok(true));
+        assert_eq!(top_rows(&display, 0), (0b0000_0001, 0));
+        assert_eq!(
+            marquee.update(&mut display, start.wrapping_add(9)),
+            Ok(false)
+        );
+        assert_eq!(frames(&display), 1);
+        assert_eq!(
+            marquee.update(&mut display, start.wrapping_add(10)),
+            Ok(true)
+        );
+        assert_eq!(top_rows(&display, 0), (0b0000_0010, 0b0000_0001));
+        assert_eq!(frames(&display), 2);
+
+        // the text leaves the chain on the 14th step
+        let mut now = start.wrapping_add(10);
+        for _ in 2..14 {
+            now = now.wrapping_add(10);
+            assert_eq!(marquee.update(&mut display, now), Ok(true));
+        }
+        assert_eq!(top_rows(&display, 0), (0, 0));
+        assert_eq!(frames(&display), 14);
+
+        // the chain stays blank for the gap intervals before the text enters again
+        for _ in 0..2 {
+            now = now.wrapping_add(10);
+            assert_eq!(marquee.update(&mut display, now), Ok(false));
+        }
+        assert_eq!(frames(&display), 14);
+        now = now.wrapping_add(10);
+        assert_eq!(marquee.update(&mut display, now), Ok(true));
+        assert_eq!(top_rows(&display, 0), (0b0000_0001, 0));
+    }
+}
@@ -0,0 +1,1149 @@
+//! Numeric helpers for 7-segment displays
+//!
+//! Digits are counted from the right, so digit register `Digit0`
+//! holds the rightmost digit of the display.
+
+use crate::connectors::Connector;
+use crate::{
+    bcd_byte, ssb_byte, ssb_byte_checked, DataError, DecodeMode, Digit, MAX7219, MAX_DIGITS,
+};
+
+/// Alignment of text shorter than the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+}
+
+///
+/// Text and number output of 7-segment displays, allowing code to be
+/// generic over what kind of display it writes to.
+///
+pub trait SegmentWrite {
+    ///
+    /// Writes byte string to the display, see `MAX7219::write_str`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned if the string could not be written
+    ///
+    fn write_str(
+        &mut self,
+        addr: usize,
+        string: &[u8; MAX_DIGITS],
+        dots: u8,
+    ) -> Result<(), DataError>;
+
+    ///
+    /// Writes a signed integer to the display, see `MAX7219::write_integer`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError` - returned if the integer could not be written
+    ///
+    fn write_integer(&mut self, addr: usize, value: i32) -> Result<(), DataError>;
+}
+
+/// What numeric writes do with values which do not fit the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowBehavior {
+    /// Return `DataError::Overflow` leaving the display as it was
+    Error,
+    /// Show dashes on all digits
+    Dashes,
+    /// Blank all digits
+    Blank,
+}
+
+///
+/// Segment logic of a module. Some clone modules light the segments
+/// which are off in the pattern written to them and vice versa.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentPolarity {
+    /// Segments light for set bits, as on genuine modules
+    Normal,
+    /// Segments light for cleared bits, all patterns are complemented before writing
+    Inverted,
+}
+
+/// Segments of a digit lighting an extra LED such as a colon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentBits {
+    /// The digit register counted from the right, `0` to `7`
+    pub digit: u8,
+    /// The segment bits of the digit, see `write_segments` for the bit layout
+    pub mask: u8,
+}
+
+///
+/// Where the colon and apostrophe LEDs of a module are wired to,
+/// see `set_indicators`. Modules differ, so custom maps can be
+/// built from `SegmentBits` when none of the presets fit.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indicators {
+    /// Segments lighting the colon
+    pub colon: SegmentBits,
+    /// Segments lighting the apostrophe
+    pub apostrophe: SegmentBits,
+}
+
+impl Indicators {
+    /// Colon on the decimal point after digit 2 as used by `write_time`,
+    /// apostrophe on the decimal point after digit 3
+    pub const DECIMAL_POINTS: Indicators = Indicators {
+        colon: SegmentBits {
+            digit: 2,
+            mask: CODE_B_DOT,
+        },
+        apostrophe: SegmentBits {
+            digit: 3,
+            mask: CODE_B_DOT,
+        },
+    };
+
+    /// Colon and apostrophe on their own digit 4 line next to a 4 digit
+    /// display, colon on segments B and C and apostrophe on segment A
+    pub const DIGIT_4: Indicators = Indicators {
+        colon: SegmentBits {
+            digit: 4,
+            mask: 0b0011_0000,
+        },
+        apostrophe: SegmentBits {
+            digit: 4,
+            mask: 0b0100_0000,
+        },
+    };
+}
+
+/// Characters of the hexadecimal digits as shown by `write_hex`
+const HEX_DIGITS: &[u8; 16] = b"0123456789AbCdEF";
+
+/// Scales of each decimal place shown by `write_float`
+#[cfg(feature = "float")]
+const POWERS_OF_TEN: [f32; MAX_DIGITS + 1] = [
+    1.0,
+    10.0,
+    100.0,
+    1_000.0,
+    10_000.0,
+    100_000.0,
+    1_000_000.0,
+    10_000_000.0,
+    100_000_000.0,
+];
+
+/// Code B font value of a blank digit
+const CODE_B_BLANK: u8 = 0x0F;
+
+/// Code B font value of a dash
+const CODE_B_DASH: u8 = 0x0A;
+
+/// Bit turning the decimal point on in Code B decode
+const CODE_B_DOT: u8 = 0b1000_0000;
+
+impl<CONNECTOR, const N: usize> MAX7219<CONNECTOR, N>
+where
+    CONNECTOR: Connector,
+{
+    ///
+    /// Sets what numeric writes such as `write_integer` and `write_str_chain` do
+    /// with values which do not fit the display, `OverflowBehavior::Error` by default
+    ///
+    /// # Arguments
+    ///
+    /// * `behavior` - how overflowing values are handled
+    ///
+    pub fn set_overflow_behavior(&mut self, behavior: OverflowBehavior) {
+        self.overflow = behavior;
+    }
+
+    ///
+    /// Sets where the colon and apostrophe LEDs are wired to,
+    /// `Indicators::DECIMAL_POINTS` by default
+    ///
+    /// # Arguments
+    ///
+    /// * `indicators` - the segments of the colon and apostrophe
+    ///
+    pub fn set_indicators(&mut self, indicators: Indicators) {
+        self.indicators = indicators;
+    }
+
+    ///
+    /// Sets the segment logic of the display, `SegmentPolarity::Normal` by default.
+    /// Patterns are complemented on the way to the chip only, so `get_digit` keeps
+    /// returning them as written. The current digits are sent again right away.
+    /// Kept apart from `set_inverted` for matrices, the two cancel out when both
+    /// are set so a negative image on an inverted module is sent as written.
+    ///
+    /// Only raw segment writes such as `write_segments` and `write_text` can be
+    /// complemented, Code B decoded digits do not show correctly on inverted modules.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `polarity` - the segment logic of the module
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_segment_polarity(
+        &mut self,
+        addr: usize,
+        polarity: SegmentPolarity,
+    ) -> Result<(), DataError> {
+        self.check_addr(addr)?;
+
+        self.polarities[addr] = polarity;
+        for digit in Digit::all() {
+            let value = self.digits[addr][digit.index() as usize];
+            self.write_register(addr, digit.register(), value)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Turns the colon on or off, keeping the other segments of its digit as last written.
+    /// Segments other than the decimal point only light on digits without Code B decode.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `on` - whether to turn the colon on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if the colon digit is outside of the display
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_colon(&mut self, addr: usize, on: bool) -> Result<(), DataError> {
+        self.set_segment_bits(addr, self.indicators.colon, on)
+    }
+
+    ///
+    /// Turns the apostrophe on or off, see `set_colon`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `on` - whether to turn the apostrophe on or off
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if the apostrophe digit is outside of the display
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_apostrophe(&mut self, addr: usize, on: bool) -> Result<(), DataError> {
+        self.set_segment_bits(addr, self.indicators.apostrophe, on)
+    }
+
+    ///
+    /// Writes a signed integer right-aligned to the display using Code B decode.
+    /// Negative values get a leading dash and unused digits are blanked.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `value` - the integer to display
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::Overflow` - returned if the value including sign needs more than 8 digits,
+    ///   unless shown as set by `set_overflow_behavior`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_integer(&mut self, addr: usize, value: i32) -> Result<(), DataError> {
+        let digits = code_b_integer(value, 1);
+
+        self.write_code_b_checked(addr, digits)
+    }
+
+    ///
+    /// Writes a number right-aligned within a field of digits using Code B decode,
+    /// leaving the digits outside of the field as they are. Allows several fields
+    /// on one display, e.g. `12  34` from two fields of width 2.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `value` - the number to display
+    /// * `start_digit` - the rightmost digit of the field counted from the right, `0` to `7`
+    /// * `width` - the number of digits in the field, unused ones are blanked
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if the field is empty or outside of the display
+    /// * `DataError::Overflow` - returned if the value needs more than `width` digits,
+    ///   unless shown as set by `set_overflow_behavior` within the field
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_number_at(
+        &mut self,
+        addr: usize,
+        value: u16,
+        start_digit: u8,
+        width: u8,
+    ) -> Result<(), DataError> {
+        if width == 0 || start_digit as usize + width as usize > MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+        self.check_addr(addr)?;
+
+        let mut digits = code_b_integer(i32::from(value), 1)?;
+        if digits[width as usize..].iter().any(|b| *b != CODE_B_BLANK) {
+            digits = match self.overflow {
+                OverflowBehavior::Error => return Err(DataError::Overflow),
+                OverflowBehavior::Dashes => [CODE_B_DASH; MAX_DIGITS],
+                OverflowBehavior::Blank => [CODE_B_BLANK; MAX_DIGITS],
+            };
+        }
+
+        let field = (((1_u16 << width) - 1) as u8) << start_digit;
+        self.set_decode_mask(addr, self.decode_masks[addr] | field)?;
+        for (digit, b) in (start_digit + 1..).zip(digits[..width as usize].iter()) {
+            self.write_register(addr, digit, *b)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes a fixed-point decimal right-aligned to the display using Code B decode,
+    /// lighting the decimal point after the digit at `point_pos` counted from the right.
+    /// For example `write_decimal(0, 1234, 2)` shows `12.34`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `value` - the digits of the decimal to display
+    /// * `point_pos` - the number of digits after the decimal point, `0` to `7`
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidPosition` - returned if `point_pos` is outside of the display
+    /// * `DataError::Overflow` - returned if the value including sign needs more than 8 digits,
+    ///   unless shown as set by `set_overflow_behavior`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_decimal(
+        &mut self,
+        addr: usize,
+        value: i32,
+        point_pos: u8,
+    ) -> Result<(), DataError> {
+        if point_pos as usize >= MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+
+        let digits = code_b_integer(value, point_pos as usize + 1).map(|mut digits| {
+            digits[point_pos as usize] |= CODE_B_DOT;
+            digits
+        });
+
+        self.write_code_b_checked(addr, digits)
+    }
+
+    ///
+    /// Writes a float right-aligned to the display using Code B decode, showing
+    /// as many of up to `max_decimals` decimal places as fit and rounding the rest.
+    /// For example `write_float(0, -3.14159, 3)` shows `-3.142`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `value` - the float to display
+    /// * `max_decimals` - the most digits to show after the decimal point
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::Overflow` - returned if the integer part including sign needs
+    ///   more than 8 digits or the value is not a number, unless shown as set by
+    ///   `set_overflow_behavior`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    #[cfg(feature = "float")]
+    pub fn write_float(
+        &mut self,
+        addr: usize,
+        value: f32,
+        max_decimals: u8,
+    ) -> Result<(), DataError> {
+        let negative = value < 0.0;
+        let abs = if negative { -value } else { value };
+
+        let mut decimals = core::cmp::min(max_decimals as usize, MAX_DIGITS - 1);
+        loop {
+            let scaled = abs * POWERS_OF_TEN[decimals] + 0.5;
+            if scaled < POWERS_OF_TEN[MAX_DIGITS] {
+                let scaled = scaled as i32;
+                let signed = if negative { -scaled } else { scaled };
+
+                if let Ok(mut digits) = code_b_integer(signed, decimals + 1) {
+                    if decimals > 0 {
+                        digits[decimals] |= CODE_B_DOT;
+                    }
+
+                    return self.write_code_b(addr, &digits);
+                }
+            }
+
+            if decimals == 0 {
+                return self.write_code_b_checked(addr, Err(DataError::Overflow));
+            }
+            decimals -= 1;
+        }
+    }
+
+    ///
+    /// Writes a single Code B digit, leaving the other digits as they are.
+    /// Given digit is switched to Code B decode first unless it already is,
+    /// keeping the decode mode of the other digits.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `position` - the digit to write, `Digit0` being the rightmost one
+    /// * `value` - the number to show, `0` to `9`
+    /// * `dot` - whether to turn the decimal point of the digit on
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidDigit` - returned if `value` is above `9`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn set_digit(
+        &mut self,
+        addr: usize,
+        position: Digit,
+        value: u8,
+        dot: bool,
+    ) -> Result<(), DataError> {
+        if value > 9 {
+            return Err(DataError::InvalidDigit);
+        }
+        self.check_addr(addr)?;
+
+        self.set_decode_mask(addr, self.decode_masks[addr] | 1 << position.index())?;
+
+        let mut b = value;
+        if dot {
+            b |= CODE_B_DOT;
+        }
+
+        self.write_register(addr, position.register(), b)
+    }
+
+    ///
+    /// Writes a time of day as `HH.MM` to the lowest 4 digits using Code B decode,
+    /// leaving the other digits as they are. The separator is the decimal point
+    /// after the hours, which is wired as colon on most clock modules.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `hours` - the hours to show, `0` to `23`
+    /// * `minutes` - the minutes to show, `0` to `59`
+    /// * `colon` - whether to light the separator
+    /// * `pad` - whether to show a leading zero for hours below 10 instead of a blank
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidTime` - returned if `hours` or `minutes` are out of range
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_time(
+        &mut self,
+        addr: usize,
+        hours: u8,
+        minutes: u8,
+        colon: bool,
+        pad: bool,
+    ) -> Result<(), DataError> {
+        if hours >= 24 || minutes >= 60 {
+            return Err(DataError::InvalidTime);
+        }
+        self.check_addr(addr)?;
+
+        let mut digits = [minutes % 10, minutes / 10, hours % 10, hours / 10];
+        if colon {
+            digits[2] |= CODE_B_DOT;
+        }
+        if hours < 10 && !pad {
+            digits[3] = CODE_B_BLANK;
+        }
+
+        self.set_decode_mask(addr, self.decode_masks[addr] | 0x0F)?;
+        for (digit, b) in (1..).zip(digits.iter()) {
+            self.write_register(addr, digit, *b)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes raw segment patterns to the display without decoding.
+    /// Each bit of a digit turns one segment on:
+    ///
+    /// | bit     | 7  | 6 | 5 | 4 | 3 | 2 | 1 | 0 |
+    /// |---------|----|---|---|---|---|---|---|---|
+    /// | segment | DP | A | B | C | D | E | F | G |
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `digits` - the segment patterns, first byte being the leftmost digit
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_segments(
+        &mut self,
+        addr: usize,
+        digits: &[u8; MAX_DIGITS],
+    ) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::NoDecode)?;
+
+        for (digit, b) in (1..).zip(digits.iter().rev()) {
+            self.write_register(addr, digit, *b)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Writes a value as hexadecimal digits right-aligned to the display
+    /// without decoding, as Code B has no `A` to `F`. Letters are shown as `A b C d E F`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `value` - the value to display, 8 hex digits always fit
+    /// * `pad` - whether to fill unused digits with zeros instead of blanking them
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_hex(&mut self, addr: usize, value: u32, pad: bool) -> Result<(), DataError> {
+        let mut segments = [0; MAX_DIGITS];
+        let mut rest = value;
+        for segment in segments.iter_mut().rev() {
+            *segment = ssb_byte(HEX_DIGITS[(rest & 0x0F) as usize], false);
+            rest >>= 4;
+            if rest == 0 && !pad {
+                break;
+            }
+        }
+
+        self.write_segments(addr, &segments)
+    }
+
+    ///
+    /// Writes a signed integer right-aligned to the display like `write_integer`, but
+    /// from the segment table without decoding. Keeps displays mixing numbers with
+    /// custom symbols in `NoDecode` instead of switching them to Code B.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `value` - the integer to display
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::Overflow` - returned if the value including sign needs more than 8 digits,
+    ///   unless shown as set by `set_overflow_behavior`
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_integer_segments(&mut self, addr: usize, value: i32) -> Result<(), DataError> {
+        let digits = match (code_b_integer(value, 1), self.overflow) {
+            (Ok(digits), _) => digits,
+            (Err(DataError::Overflow), OverflowBehavior::Dashes) => [CODE_B_DASH; MAX_DIGITS],
+            (Err(DataError::Overflow), OverflowBehavior::Blank) => [CODE_B_BLANK; MAX_DIGITS],
+            (Err(e), _) => return Err(e),
+        };
+
+        let mut segments = [0; MAX_DIGITS];
+        for (segment, b) in segments.iter_mut().rev().zip(digits.iter()) {
+            *segment = match *b {
+                CODE_B_DASH => ssb_byte(b'-', false),
+                CODE_B_BLANK => ssb_byte(b' ', false),
+                digit => ssb_byte(b'0' + digit, false),
+            };
+        }
+
+        self.write_segments(addr, &segments)
+    }
+
+    ///
+    /// Writes a string of up to 8 characters to the display, blanking unused digits
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - display to address as connected in series (0 -> last)
+    /// * `text` - the string to show, see `write_str` for supported characters
+    /// * `align` - which side of the display to align shorter strings to
+    /// * `dots` - u8 bit array specifying which digits get a dot, MSB being the leftmost digit (1 = dot, 0 = not)
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::InvalidLength` - returned if the string is longer than 8 characters
+    /// * `DataError::InvalidCharacter` - returned with the first byte of the first character
+    ///   without a segment pattern, nothing is written in that case
+    /// * `DataError::AddressOutOfRange` - returned if `addr` is not a connected display
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_text(
+        &mut self,
+        addr: usize,
+        text: &str,
+        align: Alignment,
+        dots: u8,
+    ) -> Result<(), DataError> {
+        let len = text.chars().count();
+        if len > MAX_DIGITS {
+            return Err(DataError::InvalidLength);
+        }
+
+        let mut string = [b' '; MAX_DIGITS];
+        let start = match align {
+            Alignment::Left => 0,
+            Alignment::Right => MAX_DIGITS - len,
+        };
+        for (b, c) in string[start..].iter_mut().zip(text.chars()) {
+            let mut utf8 = [0; 4];
+            *b = c.encode_utf8(&mut utf8).as_bytes()[0];
+            if !c.is_ascii() || ssb_byte_checked(*b).is_none() {
+                return Err(DataError::InvalidCharacter(*b));
+            }
+        }
+
+        let mut digits = [0; MAX_DIGITS];
+        for (i, (digit, b)) in digits.iter_mut().zip(string.iter()).enumerate() {
+            *digit = ssb_byte(*b, dots & (0b1000_0000 >> i) > 0);
+        }
+
+        self.write_segments(addr, &digits)
+    }
+
+    ///
+    /// Writes a string across all connected displays as one long display using
+    /// Code B decode, see `write_bcd` for the supported characters. Display 0
+    /// (last in series) shows the leftmost 8 characters, digits not covered
+    /// by the string are blanked.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - the string to show, non-ASCII characters show blank
+    /// * `align` - which end of the chain a shorter string is aligned to
+    ///
+    /// # Errors
+    ///
+    /// * `DataError::Overflow` - returned if the string is longer than the chain
+    ///   unless shown as set by `set_overflow_behavior`
+    /// * `DataError` - returned in case there was an error during data transfer
+    ///
+    pub fn write_str_chain(&mut self, text: &str, align: Alignment) -> Result<(), DataError> {
+        let capacity = self.devices * MAX_DIGITS;
+        let len = text.chars().count();
+
+        let mut frames = [[CODE_B_BLANK; MAX_DIGITS]; N];
+        if len > capacity {
+            let fill = match self.overflow {
+                OverflowBehavior::Error => return Err(DataError::Overflow),
+                OverflowBehavior::Dashes => CODE_B_DASH,
+                OverflowBehavior::Blank => CODE_B_BLANK,
+            };
+            frames = [[fill; MAX_DIGITS]; N];
+        } else {
+            let start = match align {
+                Alignment::Left => 0,
+                Alignment::Right => capacity - len,
+            };
+            for (pos, c) in (start..).zip(text.chars()) {
+                let b = if c.is_ascii() { c as u8 } else { b' ' };
+                // digits are counted from the right of each display
+                frames[pos / MAX_DIGITS][MAX_DIGITS - 1 - pos % MAX_DIGITS] = bcd_byte(b);
+            }
+        }
+
+        for (addr, digits) in frames[..self.devices].iter().enumerate() {
+            self.write_code_b(addr, digits)?;
+        }
+
+        Ok(())
+    }
+
+    // read-modify-writes given segments of a digit
+    fn set_segment_bits(
+        &mut self,
+        addr: usize,
+        bits: SegmentBits,
+        on: bool,
+    ) -> Result<(), DataError> {
+        if bits.digit as usize >= MAX_DIGITS {
+            return Err(DataError::InvalidPosition);
+        }
+        self.check_addr(addr)?;
+
+        let mut value = self.digits[addr][bits.digit as usize];
+        if on {
+            value |= bits.mask;
+        } else {
+            value &= !bits.mask;
+        }
+
+        self.write_register(addr, bits.digit + 1, value)
+    }
+
+    // writes Code B values or handles their overflow as configured
+    fn write_code_b_checked(
+        &mut self,
+        addr: usize,
+        digits: Result<[u8; MAX_DIGITS], DataError>,
+    ) -> Result<(), DataError> {
+        match (digits, self.overflow) {
+            (Ok(digits), _) => self.write_code_b(addr, &digits),
+            (Err(DataError::Overflow), OverflowBehavior::Dashes) => {
+                self.write_code_b(addr, &[CODE_B_DASH; MAX_DIGITS])
+            }
+            (Err(DataError::Overflow), OverflowBehavior::Blank) => {
+                self.write_code_b(addr, &[CODE_B_BLANK; MAX_DIGITS])
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    // writes Code B values starting with the rightmost digit
+    fn write_code_b(&mut self, addr: usize, digits: &[u8; MAX_DIGITS]) -> Result<(), DataError> {
+        self.set_decode_mode(addr, DecodeMode::CodeBDigits7_0)?;
+
+        for (digit, b) in (1..).zip(digits.iter()) {
+            self.write_register(addr, digit, *b)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<CONNECTOR, const N: usize> SegmentWrite for MAX7219<CONNECTOR, N>
+where
+    CONNECTOR: Connector,
+{
+    fn write_str(
+        &mut self,
+        addr: usize,
+        string: &[u8; MAX_DIGITS],
+        dots: u8,
+    ) -> Result<(), DataError> {
+        MAX7219::write_str(self, addr, string, dots)
+    }
+
+    fn write_integer(&mut self, addr: usize, value: i32) -> Result<(), DataError> {
+        MAX7219::write_integer(self, addr, value)
+    }
+}
+
+///
+/// Translate an integer into right-aligned Code B values
+/// starting with the rightmost digit, zero padded to `min_len` digits.
+///
+fn code_b_integer(value: i32, min_len: usize) -> Result<[u8; MAX_DIGITS], DataError> {
+    let mut digits = [CODE_B_BLANK; MAX_DIGITS];
+    let mut rest = value.unsigned_abs();
+    let mut len = 0;
+
+    loop {
+        if len == MAX_DIGITS {
+            return Err(DataError::Overflow);
+        }
+
+        digits[len] = (rest % 10) as u8;
+        rest /= 10;
+        len += 1;
+
+        if rest == 0 && len >= min_len {
+            break;
+        }
+    }
+
+    if value < 0 {
+        if len == MAX_DIGITS {
+            return Err(DataError::Overflow);
+        }
+        digits[len] = CODE_B_DASH;
+    }
+
+    Ok(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{display, Display};
+
+    // returns the values of all digits of given display, leftmost first
+    fn digits(display: &Display, addr: usize) -> [u8; MAX_DIGITS] {
+        let mut digits = [0; MAX_DIGITS];
+        for (value, digit) in digits.iter_mut().rev().zip(Digit::all()) {
+            *value = display.get_digit(addr, digit).unwrap();
+        }
+        digits
+    }
+
+    #[test]
+    fn set_digit_only_decodes_given_digit() {
+        let mut display = display(1);
+        display
+            .write_segments(0, &[0, 0, 0, 0x80, 0, 0, 0, 0])
+            .unwrap();
+
+        display
+            .set_digit(0, Digit::new(2).unwrap(), 5, false)
+            .unwrap();
+
+        assert_eq!(display.current_decode_mask(0), Ok(0b0000_0100));
+        assert_eq!(digits(&display, 0), [0, 0, 0, 0x80, 0, 5, 0, 0]);
+    }
+
+    #[test]
+    fn writes_hex_digits() {
+        let mut display = display(1);
+        let segments = |text: &[u8]| {
+            let mut segments = [0; MAX_DIGITS];
+            for (segment, b) in segments.iter_mut().zip(text.iter()) {
+                *segment = ssb_byte(*b, false);
+            }
+            segments
+        };
+
+        display.write_hex(0, 0xDEAD_BEEF, false).unwrap();
+        assert_eq!(digits(&display, 0), segments(b"dEAdbEEF"));
+        assert_eq!(display.current_decode_mask(0), Ok(0));
+
+        display.write_hex(0, 0xBEEF, false).unwrap();
+        assert_eq!(digits(&display, 0), segments(b"    bEEF"));
+
+        display.write_hex(0, 0xBEEF, true).unwrap();
+        assert_eq!(digits(&display, 0), segments(b"0000bEEF"));
+    }
+
+    #[test]
+    fn shows_overflow_as_configured() {
+        let mut display = display(1);
+
+        display.set_overflow_behavior(OverflowBehavior::Error);
+        assert_eq!(
+            display.write_integer(0, 123_456_789).err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+
+        display.set_overflow_behavior(OverflowBehavior::Dashes);
+        display.write_integer(0, 123_456_789).unwrap();
+        assert_eq!(digits(&display, 0), [CODE_B_DASH; MAX_DIGITS]);
+
+        display.set_overflow_behavior(OverflowBehavior::Blank);
+        display.write_integer(0, 123_456_789).unwrap();
+        assert_eq!(digits(&display, 0), [CODE_B_BLANK; MAX_DIGITS]);
+        assert_eq!(display.current_decode_mask(0), Ok(0xFF));
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    #[allow(clippy::approx_constant)] // the example given by the docs
+    fn rounds_floats_to_fitting_decimals() {
+        let mut display = display(1);
+        let (b, dash, dot) = (CODE_B_BLANK, CODE_B_DASH, CODE_B_DOT);
+
+        display.write_float(0, -3.14159, 3).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, dash, 3 | dot, 1, 4, 2]);
+
+        // decimals are dropped until the integer part fits
+        display.write_float(0, 1234.5, 7).unwrap();
+        assert_eq!(digits(&display, 0), [1, 2, 3, 4 | dot, 5, 0, 0, 0]);
+        display.write_float(0, -1234.5, 7).unwrap();
+        assert_eq!(digits(&display, 0), [dash, 1, 2, 3, 4 | dot, 5, 0, 0]);
+
+        // no minus sign in front of a zero
+        display.write_float(0, -0.0004, 3).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, dot, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "float")]
+    #[test]
+    fn shows_float_overflow_as_configured() {
+        let mut display = display(1);
+
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 123_456_789.0].iter() {
+            display.set_overflow_behavior(OverflowBehavior::Error);
+            assert_eq!(
+                display.write_float(0, *value, 2).err(),
+                Some(DataError::Overflow)
+            );
+            assert_eq!(display.connector().frame_count(), 0);
+
+            display.set_overflow_behavior(OverflowBehavior::Dashes);
+            display.write_float(0, *value, 2).unwrap();
+            assert_eq!(digits(&display, 0), [CODE_B_DASH; MAX_DIGITS]);
+
+            display.set_overflow_behavior(OverflowBehavior::Blank);
+            display.write_float(0, *value, 2).unwrap();
+            assert_eq!(digits(&display, 0), [CODE_B_BLANK; MAX_DIGITS]);
+            display.connector_mut().clear();
+        }
+    }
+
+    #[test]
+    fn writes_integers_right_aligned() {
+        let mut display = display(1);
+        let (b, dash) = (CODE_B_BLANK, CODE_B_DASH);
+
+        display.write_integer(0, 0).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, b, b, b, 0]);
+        assert_eq!(display.current_decode_mask(0), Ok(0xFF));
+
+        display.write_integer(0, 42).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, b, b, 4, 2]);
+
+        display.write_integer(0, -7).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, b, b, dash, 7]);
+
+        display.write_integer(0, 99_999_999).unwrap();
+        assert_eq!(digits(&display, 0), [9; MAX_DIGITS]);
+
+        display.write_integer(0, -9_999_999).unwrap();
+        assert_eq!(digits(&display, 0), [dash, 9, 9, 9, 9, 9, 9, 9]);
+
+        // the sign needs a digit of its own
+        display.connector_mut().clear();
+        assert_eq!(
+            display.write_integer(0, -10_000_000).err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(
+            display.write_integer(0, i32::MIN).err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+    }
+
+    #[test]
+    fn writes_decimals_with_point_and_leading_zeros() {
+        let mut display = display(1);
+        let (b, dash, dot) = (CODE_B_BLANK, CODE_B_DASH, CODE_B_DOT);
+
+        display.write_decimal(0, 5, 2).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, b, dot, 0, 5]);
+
+        display.write_decimal(0, -125, 1).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, dash, 1, 2 | dot, 5]);
+
+        display.write_decimal(0, 1234, 0).unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, 1, 2, 3, 4 | dot]);
+
+        // 8 digits padded for the point leave no room for the sign
+        assert_eq!(
+            display.write_decimal(0, -5, 7).err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(
+            display.write_decimal(0, 5, 8).err(),
+            Some(DataError::InvalidPosition)
+        );
+    }
+
+    #[test]
+    fn writes_time_to_lowest_digits() {
+        let mut display = display(1);
+        let (b, dot) = (CODE_B_BLANK, CODE_B_DOT);
+        display
+            .write_segments(0, &[1, 2, 3, 4, 0, 0, 0, 0])
+            .unwrap();
+
+        display.write_time(0, 9, 5, true, false).unwrap();
+        assert_eq!(digits(&display, 0), [1, 2, 3, 4, b, 9 | dot, 0, 5]);
+        assert_eq!(display.current_decode_mask(0), Ok(0x0F));
+
+        display.write_time(0, 9, 5, false, true).unwrap();
+        assert_eq!(digits(&display, 0), [1, 2, 3, 4, 0, 9, 0, 5]);
+
+        display.write_time(0, 23, 59, true, false).unwrap();
+        assert_eq!(digits(&display, 0), [1, 2, 3, 4, 2, 3 | dot, 5, 9]);
+
+        display.connector_mut().clear();
+        assert_eq!(
+            display.write_time(0, 24, 0, true, false).err(),
+            Some(DataError::InvalidTime)
+        );
+        assert_eq!(
+            display.write_time(0, 12, 60, true, false).err(),
+            Some(DataError::InvalidTime)
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+    }
+
+    #[test]
+    fn writes_text_aligned_with_mapping() {
+        let mut display = display(1);
+        let (h, i, four, two) = (0x37, 0x30, 0x33, 0x6D);
+
+        display.write_text(0, "Hi", Alignment::Left, 0).unwrap();
+        assert_eq!(digits(&display, 0), [h, i, 0, 0, 0, 0, 0, 0]);
+
+        display
+            .write_text(0, "42", Alignment::Right, 0b0000_0010)
+            .unwrap();
+        assert_eq!(digits(&display, 0), [0, 0, 0, 0, 0, 0, four | 0x80, two]);
+
+        // unmappable characters fail before anything is sent
+        display.connector_mut().clear();
+        assert_eq!(
+            display.write_text(0, "on M", Alignment::Left, 0).err(),
+            Some(DataError::InvalidCharacter(b'M'))
+        );
+        assert_eq!(
+            display.write_text(0, "°C", Alignment::Left, 0).err(),
+            Some(DataError::InvalidCharacter(0xC2))
+        );
+        assert_eq!(display.connector().frame_count(), 0);
+        assert_eq!(digits(&display, 0), [0, 0, 0, 0, 0, 0, four | 0x80, two]);
+    }
+
+    #[test]
+    fn splits_chain_strings_over_displays() {
+        let mut display = display(2);
+        let (b, dash) = (CODE_B_BLANK, CODE_B_DASH);
+
+        // display 0 shows the leftmost characters
+        display
+            .write_str_chain("123456789", Alignment::Left)
+            .unwrap();
+        assert_eq!(digits(&display, 0), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(digits(&display, 1), [9, b, b, b, b, b, b, b]);
+        assert_eq!(display.current_decode_mask(1), Ok(0xFF));
+
+        display
+            .write_str_chain("-12 3456789", Alignment::Right)
+            .unwrap();
+        assert_eq!(digits(&display, 0), [b, b, b, b, b, dash, 1, 2]);
+        assert_eq!(digits(&display, 1), [b, 3, 4, 5, 6, 7, 8, 9]);
+
+        assert_eq!(
+            display
+                .write_str_chain("12345678901234567", Alignment::Left)
+                .err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(digits(&display, 1), [b, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn writes_numbers_within_fields() {
+        let mut display = display(1);
+        let (b, dash) = (CODE_B_BLANK, CODE_B_DASH);
+        display
+            .write_segments(0, &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88])
+            .unwrap();
+
+        display.write_number_at(0, 12, 0, 2).unwrap();
+        display.write_number_at(0, 7, 4, 3).unwrap();
+        assert_eq!(digits(&display, 0), [0x11, b, b, 7, 0x55, 0x66, 1, 2]);
+        assert_eq!(display.current_decode_mask(0), Ok(0b0111_0011));
+
+        // the field may end at the leftmost digit
+        display.write_number_at(0, 9, 6, 2).unwrap();
+        assert_eq!(digits(&display, 0), [b, 9, b, 7, 0x55, 0x66, 1, 2]);
+
+        for (start_digit, width) in [(7, 2), (8, 1), (0, 0), (0, 9)].iter() {
+            assert_eq!(
+                display.write_number_at(0, 1, *start_digit, *width).err(),
+                Some(DataError::InvalidPosition)
+            );
+        }
+        assert_eq!(
+            display.write_number_at(0, 123, 6, 2).err(),
+            Some(DataError::Overflow)
+        );
+        assert_eq!(digits(&display, 0), [b, 9, b, 7, 0x55, 0x66, 1, 2]);
+
+        display.set_overflow_behavior(OverflowBehavior::Dashes);
+        display.write_number_at(0, 123, 6, 2).unwrap();
+        assert_eq!(digits(&display, 0), [dash, dash, b, 7, 0x55, 0x66, 1, 2]);
+    }
+
+    #[test]
+    fn writes_integer_segments_without_decoding() {
+        let mut display = display(1);
+        let segments = [0x7E, 0x30, 0x6D, 0x79, 0x33, 0x5B, 0x5F, 0x70, 0x7F, 0x7B];
+        let minus = 0x01;
+        display.write_integer(0, 42).unwrap();
+
+        display.write_integer_segments(0, -1234567).unwrap();
+        let mut expected = [minus; MAX_DIGITS];
+        expected[1..].copy_from_slice(&segments[1..8]);
+        assert_eq!(digits(&display, 0), expected);
+        assert_eq!(display.current_decode_mask(0), Ok(0x00));
+
+        display.write_integer_segments(0, 89).unwrap();
+        assert_eq!(
+            digits(&display, 0),
+            [0, 0, 0, 0, 0, 0, segments[8], segments[9]]
+        );
+
+        display.write_integer_segments(0, 0).unwrap();
+        assert_eq!(digits(&display, 0), [0, 0, 0, 0, 0, 0, 0, segments[0]]);
+    }
+
+    #[test]
+    fn inverts_segments_apart_from_matrix_image() {
+        let mut display = display(1);
+        // value last sent to the leftmost digit
+        let wire = |display: &Display| {
+            display
+                .connector()
+                .commands()
+                .filter(|(_, register, _)| *register == 8)
+                .last()
+                .map(|(_, _, value)| value)
+        };
+
+        display
+            .set_segment_polarity(0, SegmentPolarity::Inverted)
+            .unwrap();
+        display
+            .write_segments(0, &[0x30, 0, 0, 0, 0, 0, 0, 0])
+            .unwrap();
+        assert_eq!(wire(&display), Some(!0x30));
+        assert_eq!(display.get_digit(0, Digit::new(7).unwrap()), Ok(0x30));
+
+        // a negative image on an inverted module is sent as written
+        #[cfg(feature = "matrix")]
+        {
+            display.set_inverted(0, true).unwrap();
+            assert_eq!(wire(&display), Some(0x30));
+            display
+                .set_segment_polarity(0, SegmentPolarity::Normal)
+                .unwrap();
+            assert_eq!(wire(&display), Some(!0x30));
+            display.set_inverted(0, false).unwrap();
+            assert_eq!(wire(&display), Some(0x30));
+        }
+    }
+}
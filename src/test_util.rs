@@ -0,0 +1,19 @@
+//! Fixtures shared by the unit tests of all modules
+
+use crate::mock::RecordingConnector;
+use crate::MAX7219;
+
+/// Driver recording its frames instead of sending them
+pub(crate) type Display = MAX7219<RecordingConnector>;
+
+// connects `devices` displays, keeping the initialization frames
+pub(crate) fn initialized(devices: usize) -> Display {
+    MAX7219::from_connector(devices, RecordingConnector::new()).expect("valid display count")
+}
+
+// connects `devices` displays and forgets the initialization frames
+pub(crate) fn display(devices: usize) -> Display {
+    let mut display = initialized(devices);
+    display.connector_mut().clear();
+    display
+}